@@ -0,0 +1,181 @@
+use crate::{
+    player::{Direction, Player, PlayerVelocity},
+    GameState,
+};
+use bevy::prelude::*;
+
+/// Frames in one row of the chicken sheet, one row per direction.
+const SHEET_COLUMNS: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Clip {
+    Idle,
+    Walk,
+    /// Played when the chicken sets off from standing still.
+    Hop,
+    Hit,
+    Death,
+}
+
+/// How a clip plays. Frames are columns of the row the chicken is facing.
+struct ClipData {
+    frames: &'static [usize],
+    frame_secs: f32,
+    looping: bool,
+}
+
+impl Clip {
+    fn data(&self) -> ClipData {
+        match self {
+            Clip::Idle => ClipData {
+                frames: &[0, 0, 0, 1],
+                frame_secs: 0.3,
+                looping: true,
+            },
+            Clip::Walk => ClipData {
+                frames: &[0, 1, 2, 3, 4, 5],
+                frame_secs: 0.08,
+                looping: true,
+            },
+            Clip::Hop => ClipData {
+                frames: &[2, 3, 4],
+                frame_secs: 0.05,
+                looping: false,
+            },
+            Clip::Hit => ClipData {
+                frames: &[0, 3, 0, 3, 0],
+                frame_secs: 0.07,
+                looping: false,
+            },
+            Clip::Death => ClipData {
+                frames: &[0, 5, 4, 3],
+                frame_secs: 0.15,
+                looping: false,
+            },
+        }
+    }
+}
+
+fn sheet_row(direction: Direction) -> usize {
+    match direction {
+        Direction::Right => 0,
+        Direction::Up => 1,
+        Direction::Left => 2,
+        Direction::Down => 3,
+    }
+}
+
+pub fn idle_sprite_index(direction: Direction) -> usize {
+    sheet_row(direction) * SHEET_COLUMNS + Clip::Idle.data().frames[0]
+}
+
+/// Sent when a clip that doesn't loop shows its last frame.
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: Clip,
+}
+
+#[derive(Component)]
+pub struct Animator {
+    clip: Clip,
+    frame: usize,
+    timer: Timer,
+    finished: bool,
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::new(Clip::Idle)
+    }
+}
+
+impl Animator {
+    fn new(clip: Clip) -> Self {
+        Self {
+            clip,
+            frame: 0,
+            timer: Timer::from_seconds(clip.data().frame_secs, TimerMode::Repeating),
+            finished: false,
+        }
+    }
+
+    /// Starts `clip` from its first frame, unless it is already playing.
+    pub fn play(&mut self, clip: Clip) {
+        if clip != self.clip || self.finished {
+            *self = Self::new(clip);
+        }
+    }
+
+    /// Whether a clip that plays once is still going. It isn't cut short.
+    fn busy(&self) -> bool {
+        !self.finished && !self.clip.data().looping
+    }
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnimationFinished>();
+
+        for state in [GameState::InGame, GameState::Endless, GameState::Attract] {
+            app.add_system_set(
+                SystemSet::on_update(state)
+                    .with_system(player_clip_system.before(animate_system))
+                    .with_system(animate_system),
+            );
+        }
+
+        // Only the death plays out while the game is frozen.
+        app.add_system_set(SystemSet::on_update(GameState::Dying).with_system(animate_system));
+    }
+}
+
+/// Picks the clip matching what each chicken is doing.
+fn player_clip_system(mut player_query: Query<(&PlayerVelocity, &mut Animator), With<Player>>) {
+    for (velocity, mut animator) in player_query.iter_mut() {
+        // A dead chicken stays on its last frame.
+        if animator.busy() || animator.clip == Clip::Death {
+            continue;
+        }
+
+        let moving = velocity.x != 0. || velocity.y != 0.;
+        let clip = match (moving, animator.clip) {
+            (false, _) => Clip::Idle,
+            (true, Clip::Idle) => Clip::Hop,
+            (true, _) => Clip::Walk,
+        };
+        animator.play(clip);
+    }
+}
+
+fn animate_system(
+    mut animator_query: Query<(Entity, &mut Animator, &mut TextureAtlasSprite, &Direction)>,
+    mut finished: EventWriter<AnimationFinished>,
+    time: Res<Time>,
+) {
+    for (entity, mut animator, mut sprite, direction) in animator_query.iter_mut() {
+        let data = animator.clip.data();
+
+        if !animator.finished {
+            animator.timer.tick(time.delta());
+
+            for _ in 0..animator.timer.times_finished_this_tick() {
+                if animator.frame + 1 < data.frames.len() {
+                    animator.frame += 1;
+                } else if data.looping {
+                    animator.frame = 0;
+                } else {
+                    animator.finished = true;
+                    finished.send(AnimationFinished {
+                        entity,
+                        clip: animator.clip,
+                    });
+                    break;
+                }
+            }
+        }
+
+        sprite.index = sheet_row(*direction) * SHEET_COLUMNS + data.frames[animator.frame];
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use crate::{
+    autopilot::{autopilot_system, Autopilot},
+    crosswalk::traffic_light_system,
+    enemy::{
+        crossing_signal_system, enemy_collides_with_player, move_enemy_system, spawn_enemy_system,
+        spawn_train_system, Enemy,
+    },
+    in_game::{despawn_in_game_system, in_game_setup_system, sidewalk_hit_system},
+    level::Level,
+    menu::Ancestor,
+    player::{
+        move_player_system, spawn_player_entity, Player, PlayerState, Sidewalk, PLAYER_START_Y,
+    },
+    river::{platform_under, platforms_in, river_at, Platform, Turtle},
+    transition::{Effect, Transitions},
+    GameAssets, GameState,
+};
+use bevy::prelude::*;
+
+const IDLE_TIMEOUT: u64 = 10;
+const MENU_ALPHA_ATTRACT: f32 = 0.6;
+
+#[derive(Resource)]
+pub struct MenuIdle {
+    pub timer: Timer,
+}
+
+impl Default for MenuIdle {
+    fn default() -> Self {
+        Self {
+            timer: Timer::new(Duration::from_secs(IDLE_TIMEOUT), TimerMode::Once),
+        }
+    }
+}
+
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MenuIdle::default())
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(reset_idle_system))
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(menu_idle_system))
+            .add_system_set(SystemSet::on_pause(GameState::Menu).with_system(fade_menu_system))
+            .add_system_set(
+                SystemSet::on_resume(GameState::Menu)
+                    .with_system(restore_menu_system)
+                    .with_system(reset_idle_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Attract)
+                    .with_system(in_game_setup_system)
+                    .with_system(spawn_attract_player_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Attract)
+                    .with_system(autopilot_system)
+                    .with_system(move_player_system)
+                    .with_system(spawn_enemy_system)
+                    .with_system(spawn_train_system.before(spawn_enemy_system))
+                    .with_system(crossing_signal_system)
+                    .with_system(traffic_light_system.before(move_enemy_system))
+                    .with_system(move_enemy_system)
+                    .with_system(sidewalk_hit_system)
+                    .with_system(attract_hit_system)
+                    .with_system(attract_exit_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Attract).with_system(despawn_in_game_system),
+            );
+    }
+}
+
+fn any_input(
+    kb: &Input<KeyCode>,
+    mouse: &Input<MouseButton>,
+    cursor_moved: &mut EventReader<CursorMoved>,
+) -> bool {
+    let moved = cursor_moved.iter().count() > 0;
+    kb.get_just_pressed().len() > 0 || mouse.get_just_pressed().len() > 0 || moved
+}
+
+fn reset_idle_system(mut idle: ResMut<MenuIdle>) {
+    idle.timer.reset();
+}
+
+fn menu_idle_system(
+    kb: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    time: Res<Time>,
+    mut idle: ResMut<MenuIdle>,
+    mut transitions: ResMut<Transitions>,
+) {
+    if any_input(&kb, &mouse, &mut cursor_moved) {
+        idle.timer.reset();
+        return;
+    }
+
+    idle.timer.tick(time.delta());
+
+    if idle.timer.just_finished() {
+        transitions.push(GameState::Attract, Effect::Fade);
+    }
+}
+
+fn fade_menu_system(mut menu_query: Query<&mut BackgroundColor, With<Ancestor>>) {
+    for mut background in menu_query.iter_mut() {
+        background.0.set_a(MENU_ALPHA_ATTRACT);
+    }
+}
+
+fn restore_menu_system(mut menu_query: Query<&mut BackgroundColor, With<Ancestor>>) {
+    for mut background in menu_query.iter_mut() {
+        background.0.set_a(1.);
+    }
+}
+
+fn spawn_attract_player_system(mut commands: Commands, game_textures: Res<GameAssets>) {
+    let player = spawn_player_entity(&mut commands, &game_textures, 0, 0.);
+    commands.entity(player).insert(Autopilot);
+}
+
+fn attract_hit_system(
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Player>)>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>), Without<Player>>,
+    mut player_query: Query<(&mut Transform, &mut PlayerState), With<Player>>,
+    level: Res<Level>,
+) {
+    for (mut player_tf, mut player_state) in player_query.iter_mut() {
+        let hit = enemy_query
+            .iter()
+            .any(|enemy_tf| enemy_collides_with_player(enemy_tf, &player_tf));
+        let drowned = river_at(&level, player_tf.translation.y).is_some_and(|river_y| {
+            platform_under(&player_tf, platforms_in(river_y, platform_query.iter())).is_none()
+        });
+
+        // The demo never ends: a hit chicken just starts over from the bottom sidewalk.
+        if hit || drowned {
+            player_tf.translation.y = PLAYER_START_Y;
+            player_state.last_sidewalk = Sidewalk::None;
+        }
+    }
+}
+
+fn attract_exit_system(
+    kb: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut transitions: ResMut<Transitions>,
+) {
+    if any_input(&kb, &mouse, &mut cursor_moved) {
+        transitions.pop(Effect::Fade);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    enemy::{Enemy, EnemyVelocity, TrafficModifiers, ENEMY_DIM, ENEMY_SCALE},
+    level::{Lane, LaneKind, Level, Obstacle},
+    player::{Direction, Player, PlayerState, PlayerVelocity, Sidewalk, ARENA_LEFT, ARENA_RIGHT},
+    river::{Platform, Turtle, RIVER_HALF_HEIGHT},
+    BASE_SPEED, PLAYER_DIM, TIME_STEP,
+};
+use bevy::{prelude::*, utils::HashMap};
+
+// The enemy hitbox is square (see `enemy_hit_player_system`), so its half height is the
+// same as its half width.
+const ENEMY_HALF_SIZE: f32 = ENEMY_DIM.0 * ENEMY_SCALE / 2.;
+const PLAYER_HALF_SIZE: f32 = PLAYER_DIM;
+const LANE_HALF_HEIGHT: f32 = ENEMY_HALF_SIZE + PLAYER_HALF_SIZE;
+const SAFETY_MARGIN: f32 = 12.;
+/// How close an obstacle ahead has to be before the chicken walks around it.
+const OBSTACLE_LOOKAHEAD: f32 = 8.;
+
+#[derive(Component)]
+pub struct Autopilot;
+
+/// A car as seen by the autopilot: its position and horizontal velocity.
+pub struct CarSnapshot {
+    pub position: Vec2,
+    pub velocity_x: f32,
+}
+
+/// A river platform as seen by the autopilot. `safe_secs` is how long it can still be stood
+/// on, infinite for logs.
+pub struct PlatformSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub half_width: f32,
+    pub velocity_x: f32,
+    pub safe_secs: f32,
+}
+
+/// Picks the next move for a chicken at `player` heading towards the top sidewalk when
+/// `heading_up` is true. Returns `None` when the chicken should wait at the edge of the
+/// next lane because a car would hit it while crossing, or no platform is there to board.
+pub fn plan_direction(
+    player: Vec2,
+    heading_up: bool,
+    lanes: &[Lane],
+    cars: &[CarSnapshot],
+    platforms: &[PlatformSnapshot],
+) -> Option<Direction> {
+    let (sign, direction) = if heading_up {
+        (1., Direction::Up)
+    } else {
+        (-1., Direction::Down)
+    };
+    let step = TIME_STEP * BASE_SPEED;
+
+    let next_lane = lanes
+        .iter()
+        .filter(|lane| (player.y - lane.y).abs() >= LANE_HALF_HEIGHT)
+        .map(|lane| (lane, (lane.y - player.y) * sign - LANE_HALF_HEIGHT))
+        .filter(|(_, distance)| *distance >= 0.)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    // Already inside a lane or nothing left to cross: keep going.
+    let Some((lane, distance)) = next_lane else {
+        return Some(direction);
+    };
+
+    if distance > step {
+        return Some(direction);
+    }
+
+    if lane.kind == LaneKind::River {
+        let water_distance = (lane.y - player.y).abs() - RIVER_HALF_HEIGHT;
+        let board_at = water_distance / BASE_SPEED;
+        let ashore_at = board_at + RIVER_HALF_HEIGHT * 2. / BASE_SPEED;
+
+        // Once aboard the chicken drifts with the platform, so it only has to be under the
+        // chicken when it steps in and stay up until it steps out.
+        let boardable = platforms
+            .iter()
+            .filter(|platform| (platform.y - lane.y).abs() < 1.)
+            .any(|platform| {
+                let x = platform.x + platform.velocity_x * BASE_SPEED * board_at;
+                (x - player.x).abs() < platform.half_width - SAFETY_MARGIN
+                    && platform.safe_secs > ashore_at
+            });
+
+        return boardable.then_some(direction);
+    }
+
+    let time_to_cross = (distance + LANE_HALF_HEIGHT * 2.) / BASE_SPEED;
+    let reach = ENEMY_HALF_SIZE + PLAYER_HALF_SIZE + SAFETY_MARGIN;
+
+    let blocked = cars
+        .iter()
+        .filter(|car| (car.position.y - lane.y).abs() < 1.)
+        .any(|car| {
+            let offset = car.position.x - player.x;
+            let speed = car.velocity_x * BASE_SPEED;
+
+            if speed == 0. {
+                return offset.abs() < reach;
+            }
+
+            let enter = (-reach - offset) / speed;
+            let leave = (reach - offset) / speed;
+            let (start, end) = (enter.min(leave), enter.max(leave));
+
+            start < time_to_cross && end > 0.
+        });
+
+    if blocked {
+        None
+    } else {
+        Some(direction)
+    }
+}
+
+/// Which way to walk around an obstacle right in the way of a chicken at `player` heading
+/// up (`sign` 1) or down (-1): towards its nearer end, unless that end is against the
+/// edge of the arena.
+fn sidestep(player: Vec2, sign: f32, obstacles: &[(Vec2, Vec2)]) -> Option<Direction> {
+    let half = Vec2::splat(PLAYER_HALF_SIZE);
+
+    let (center, size) = obstacles.iter().find(|(center, size)| {
+        let reach = half + *size / 2.;
+        let ahead = (center.y - player.y) * sign;
+        (player.x - center.x).abs() < reach.x && ahead > 0. && ahead < reach.y + OBSTACLE_LOOKAHEAD
+    })?;
+
+    let reach = half.x + size.x / 2. + 1.;
+    let left = center.x - reach;
+    let right = center.x + reach;
+    let left_nearer = player.x - left <= right - player.x;
+
+    if (left_nearer && left > ARENA_LEFT) || right >= ARENA_RIGHT {
+        Some(Direction::Left)
+    } else {
+        Some(Direction::Right)
+    }
+}
+
+pub fn autopilot_system(
+    mut player_query: Query<
+        (
+            &Transform,
+            &PlayerState,
+            &mut PlayerVelocity,
+            &mut Direction,
+        ),
+        With<Autopilot>,
+    >,
+    enemy_query: Query<(&Transform, &EnemyVelocity), With<Enemy>>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>)>,
+    obstacle_query: Query<(&Transform, &Obstacle)>,
+    traffic: Res<TrafficModifiers>,
+    level: Res<Level>,
+) {
+    let cars: Vec<CarSnapshot> = enemy_query
+        .iter()
+        .map(|(enemy_tf, enemy_velocity)| CarSnapshot {
+            position: enemy_tf.translation.truncate(),
+            velocity_x: enemy_velocity.x * traffic.speed.value(),
+        })
+        .collect();
+
+    let platforms: Vec<PlatformSnapshot> = platform_query
+        .iter()
+        .map(|(platform_tf, platform, turtle)| PlatformSnapshot {
+            x: platform_tf.translation.x,
+            y: platform_tf.translation.y,
+            half_width: platform.width / 2.,
+            velocity_x: platform.velocity_x * traffic.speed.value(),
+            safe_secs: turtle.map_or(f32::INFINITY, |turtle| {
+                turtle.secs_until_dive() / traffic.speed.value()
+            }),
+        })
+        .collect();
+
+    let obstacles: Vec<(Vec2, Vec2)> = obstacle_query
+        .iter()
+        .map(|(obstacle_tf, obstacle)| (obstacle_tf.translation.truncate(), obstacle.size))
+        .collect();
+
+    for (player_tf, player_state, mut velocity, mut direction) in player_query.iter_mut() {
+        let heading_up = player_state.last_sidewalk != Sidewalk::Top;
+        let sign = if heading_up { 1. } else { -1. };
+
+        if let Some(around) = sidestep(player_tf.translation.truncate(), sign, &obstacles) {
+            velocity.x = if around == Direction::Left { -1. } else { 1. };
+            velocity.y = 0.;
+            *direction = around;
+            continue;
+        }
+
+        match plan_direction(
+            player_tf.translation.truncate(),
+            heading_up,
+            &level.lanes,
+            &cars,
+            &platforms,
+        ) {
+            Some(next) => {
+                velocity.x = 0.;
+                velocity.y = if next == Direction::Up { 1. } else { -1. };
+                *direction = next;
+            }
+            None => {
+                velocity.x = 0.;
+                velocity.y = 0.;
+            }
+        }
+    }
+}
+
+pub fn toggle_autopilot_system(
+    mut commands: Commands,
+    kb: Res<Input<KeyCode>>,
+    player_query: Query<(Entity, Option<&Autopilot>), With<Player>>,
+) {
+    if !kb.just_pressed(KeyCode::P) {
+        return;
+    }
+
+    for (player_ent, autopilot) in player_query.iter() {
+        if autopilot.is_some() {
+            commands.entity(player_ent).remove::<Autopilot>();
+        } else {
+            commands.entity(player_ent).insert(Autopilot);
+        }
+    }
+}
+
+pub fn autopilot_report_system(
+    player_query: Query<(&Player, &PlayerState), With<Autopilot>>,
+    time: Res<Time>,
+    mut last_levels: Local<HashMap<usize, u8>>,
+) {
+    for (player, player_state) in player_query.iter() {
+        let last_level = last_levels.entry(player.id).or_insert(player_state.level);
+
+        if *last_level != player_state.level {
+            *last_level = player_state.level;
+
+            info!(
+                "autopilot P{} reached level {} at {:.1}s",
+                player.id + 1,
+                player_state.level,
+                time.elapsed_seconds()
+            );
+        }
+    }
+}
@@ -0,0 +1,127 @@
+#[allow(dead_code)]
+#[path = "../protocol.rs"]
+mod protocol;
+
+use std::{
+    env,
+    net::{SocketAddr, UdpSocket},
+};
+
+use protocol::{Packet, DEFAULT_RELAY_ADDR, MAX_PEERS};
+
+struct Race {
+    seed: u64,
+    peers: Vec<SocketAddr>,
+    active: Vec<bool>,
+}
+
+impl Race {
+    fn start(&self, socket: &UdpSocket, peer: usize) {
+        let packet = Packet::Start {
+            seed: self.seed,
+            peer: peer as u8,
+            peers: self.peers.len() as u8,
+        };
+        send(socket, &packet.encode(), self.peers[peer]);
+    }
+}
+
+/// One racer that can't be reached must not take the relay down for the others, so send
+/// errors are only logged. A racer that missed its start packet asks for it again.
+fn send(socket: &UdpSocket, bytes: &[u8], addr: SocketAddr) {
+    if let Err(err) = socket.send_to(bytes, addr) {
+        eprintln!("could not send to {}: {}", addr, err);
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let mut args = env::args().skip(1);
+    let bind = args
+        .next()
+        .unwrap_or_else(|| DEFAULT_RELAY_ADDR.to_string());
+    let players = args
+        .next()
+        .and_then(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(2)
+        .clamp(2, MAX_PEERS);
+
+    let socket = UdpSocket::bind(&bind)?;
+    println!("relay listening on {} for {} racers", bind, players);
+
+    let mut lobby: Vec<SocketAddr> = Vec::new();
+    let mut race: Option<Race> = None;
+    let mut buffer = [0u8; 64];
+
+    loop {
+        // On some platforms a racer that went away shows up here as a receive error.
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) => {
+                eprintln!("could not receive: {}", err);
+                continue;
+            }
+        };
+        let Some(packet) = Packet::decode(&buffer[..len]) else {
+            continue;
+        };
+
+        match packet {
+            Packet::Hello => {
+                // A racer that missed its start packet keeps saying hello.
+                if let Some(race) = &race {
+                    if let Some(peer) = race.peers.iter().position(|addr| *addr == from) {
+                        race.start(&socket, peer);
+                        continue;
+                    }
+                }
+
+                if !lobby.contains(&from) {
+                    println!("{} joined the lobby", from);
+                    lobby.push(from);
+                }
+
+                if lobby.len() == players && race.is_none() {
+                    let peers = std::mem::take(&mut lobby);
+                    let new_race = Race {
+                        seed: rand::random(),
+                        active: vec![true; peers.len()],
+                        peers,
+                    };
+
+                    for peer in 0..new_race.peers.len() {
+                        new_race.start(&socket, peer);
+                    }
+                    println!("race started with seed {}", new_race.seed);
+                    race = Some(new_race);
+                }
+            }
+            Packet::Input { peer, .. } | Packet::Leave { peer, .. } => {
+                if let Some(waiting) = lobby.iter().position(|addr| *addr == from) {
+                    println!("{} left the lobby", from);
+                    lobby.remove(waiting);
+                    continue;
+                }
+
+                let Some(current) = &mut race else {
+                    continue;
+                };
+
+                for addr in current.peers.iter().filter(|addr| **addr != from) {
+                    send(&socket, &buffer[..len], *addr);
+                }
+
+                if let Packet::Leave { .. } = packet {
+                    if let Some(active) = current.active.get_mut(usize::from(peer)) {
+                        *active = false;
+                    }
+
+                    if current.active.iter().all(|active| !active) {
+                        println!("race finished");
+                        race = None;
+                    }
+                }
+            }
+            Packet::Start { .. } => {}
+        }
+    }
+}
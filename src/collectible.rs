@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use crate::{
+    level::Level,
+    net::Lockstep,
+    player::{GameMode, Player, PlayerMode, Session, MAX_LIVES},
+    powerup::{ActiveEffects, PowerUpKind, POWER_UP_KINDS},
+    transition::Transitions,
+    GameState, COLOR_YELLOW, PLAYER_DIM, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::math::Vec3Swizzles;
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::{thread_rng, Rng};
+
+const CORN_POINTS: u32 = 10;
+const CORN_DIM: Vec2 = Vec2::new(10., 18.);
+const GOLDEN_EGG_DIM: Vec2 = Vec2::new(14., 18.);
+const GOLDEN_EGG_CHANCE: f64 = 0.1;
+const POWER_UP_DIM: Vec2 = Vec2::new(16., 16.);
+const POWER_UP_CHANCE: f64 = 0.15;
+const COLOR_GOLD: (f32, f32, f32) = (1., 215. / 255., 0.);
+
+const SPAWN_INTERVAL: f32 = 3.;
+const LIFETIME: f32 = 6.;
+const BLINK_BEFORE_DESPAWN: f32 = 1.5;
+const BLINK_RATE: f32 = 8.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CollectibleKind {
+    Corn,
+    GoldenEgg,
+    PowerUp(PowerUpKind),
+}
+
+impl CollectibleKind {
+    fn size(&self) -> Vec2 {
+        match self {
+            CollectibleKind::Corn => CORN_DIM,
+            CollectibleKind::GoldenEgg => GOLDEN_EGG_DIM,
+            CollectibleKind::PowerUp(_) => POWER_UP_DIM,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            CollectibleKind::Corn => Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+            CollectibleKind::GoldenEgg => Color::rgb(COLOR_GOLD.0, COLOR_GOLD.1, COLOR_GOLD.2),
+            CollectibleKind::PowerUp(kind) => kind.color(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Collectible {
+    pub kind: CollectibleKind,
+    pub lifetime: Timer,
+}
+
+#[derive(Resource)]
+pub struct CollectibleSpawnConfig {
+    pub timer: Timer,
+}
+
+pub struct CollectiblePlugin;
+
+impl Plugin for CollectiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::InGame).with_system(collectible_setup_system),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(spawn_collectible_system)
+                .with_system(collectible_lifetime_system)
+                .with_system(pickup_collectible_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::InGame).with_system(despawn_collectible_system),
+        );
+    }
+}
+
+fn collectible_setup_system(mut commands: Commands) {
+    commands.insert_resource(CollectibleSpawnConfig {
+        timer: Timer::new(
+            Duration::from_secs_f32(SPAWN_INTERVAL),
+            TimerMode::Repeating,
+        ),
+    });
+}
+
+fn spawn_collectible_system(
+    mut commands: Commands,
+    mut spawn_config: ResMut<CollectibleSpawnConfig>,
+    session: Res<Session>,
+    level: Res<Level>,
+    time: Res<Time>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    spawn_config.timer.tick(time.delta());
+
+    if !spawn_config.timer.just_finished() {
+        return;
+    }
+
+    // Collectibles are local flavour and stay out of the seeded traffic generator.
+    let mut rng = thread_rng();
+
+    let sidewalk_y = (WIN_HEIGHT + SIDE_WALK) / 2.;
+    let rows: Vec<f32> = level
+        .lanes
+        .iter()
+        .map(|lane| lane.y)
+        .chain([sidewalk_y, -sidewalk_y])
+        .collect();
+    let y = rows[rng.gen_range(0..rows.len())];
+    let x = rng.gen_range((-WIN_WIDTH / 2. + PLAYER_DIM)..(WIN_WIDTH / 2. - PLAYER_DIM));
+
+    // Nothing to pick up inside a tree; the next spawn will land somewhere else.
+    if level
+        .obstacles
+        .iter()
+        .any(|obstacle| obstacle.contains(Vec2::new(x, y)))
+    {
+        return;
+    }
+
+    // Survival is played on a single life. Power-ups change traffic, which would desync an
+    // online race.
+    let extra_lives = session.mode != PlayerMode::Versus && session.game_mode != GameMode::Survival;
+    let kind = if extra_lives && rng.gen_bool(GOLDEN_EGG_CHANCE) {
+        CollectibleKind::GoldenEgg
+    } else if lockstep.is_none() && rng.gen_bool(POWER_UP_CHANCE) {
+        CollectibleKind::PowerUp(POWER_UP_KINDS[rng.gen_range(0..POWER_UP_KINDS.len())])
+    } else {
+        CollectibleKind::Corn
+    };
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(kind.size()),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, y, 1.5),
+            ..default()
+        })
+        .insert(Collectible {
+            kind,
+            lifetime: Timer::new(Duration::from_secs_f32(LIFETIME), TimerMode::Once),
+        })
+        .insert(Name::new("Collectible"));
+}
+
+fn collectible_lifetime_system(
+    mut commands: Commands,
+    mut collectible_query: Query<(Entity, &mut Collectible, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (ent, mut collectible, mut visibility) in collectible_query.iter_mut() {
+        collectible.lifetime.tick(time.delta());
+
+        if collectible.lifetime.finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        let remaining = LIFETIME - collectible.lifetime.elapsed_secs();
+        if remaining < BLINK_BEFORE_DESPAWN {
+            visibility.is_visible = ((remaining * BLINK_RATE) as u32).is_multiple_of(2);
+        }
+    }
+}
+
+fn pickup_collectible_system(
+    mut commands: Commands,
+    collectible_query: Query<(Entity, &Collectible, &Transform)>,
+    player_query: Query<(&Player, &Transform)>,
+    mut session: ResMut<Session>,
+    mut effects: ResMut<ActiveEffects>,
+    transitions: Res<Transitions>,
+) {
+    // Nothing counts once the run is ending.
+    if transitions.is_running() {
+        return;
+    }
+
+    for (ent, collectible, collectible_tf) in collectible_query.iter() {
+        let picked_by = player_query.iter().find(|(_, player_tf)| {
+            collide(
+                player_tf.translation,
+                PLAYER_DIM * player_tf.scale.xy(),
+                collectible_tf.translation,
+                collectible.kind.size(),
+            )
+            .is_some()
+        });
+
+        let Some((player, _)) = picked_by else {
+            continue;
+        };
+
+        match collectible.kind {
+            CollectibleKind::Corn => {
+                session.corn += 1;
+                session.score += CORN_POINTS;
+            }
+            CollectibleKind::GoldenEgg => {
+                session.lives = session.lives.saturating_add(1).min(MAX_LIVES);
+            }
+            CollectibleKind::PowerUp(kind) => effects.grant(kind, player.id),
+        }
+
+        commands.entity(ent).despawn();
+    }
+}
+
+fn despawn_collectible_system(
+    mut commands: Commands,
+    collectible_query: Query<Entity, With<Collectible>>,
+) {
+    for ent in collectible_query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use crate::{enemy::TrafficModifiers, in_game::GameBackground, net::Lockstep, TIME_STEP};
+use bevy::prelude::*;
+
+pub const CROSSWALK_X: f32 = 150.;
+const CROSSWALK_HALF_WIDTH: f32 = 35.;
+const CROSSWALK_HALF_HEIGHT: f32 = 60.;
+const STRIPE_HEIGHT: f32 = 10.;
+/// Cars stop with their bumper on this line, just short of the zebra.
+pub const STOP_LINE_X: f32 = CROSSWALK_X - CROSSWALK_HALF_WIDTH - 12.;
+
+const GREEN_SECS: f32 = 6.;
+const YELLOW_SECS: f32 = 1.2;
+const RED_SECS: f32 = 4.;
+
+const LAMP_SIZE: f32 = 12.;
+const COLOR_STRIPE: (f32, f32, f32) = (0.95, 0.95, 0.95);
+const COLOR_POLE: (f32, f32, f32) = (0.15, 0.15, 0.15);
+const COLOR_LAMP_OFF: (f32, f32, f32) = (0.3, 0.3, 0.3);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LightPhase {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl LightPhase {
+    fn duration(&self) -> f32 {
+        match self {
+            LightPhase::Green => GREEN_SECS,
+            LightPhase::Yellow => YELLOW_SECS,
+            LightPhase::Red => RED_SECS,
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            LightPhase::Green => LightPhase::Yellow,
+            LightPhase::Yellow => LightPhase::Red,
+            LightPhase::Red => LightPhase::Green,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            LightPhase::Green => Color::rgb(0.2, 0.85, 0.3),
+            LightPhase::Yellow => Color::rgb(1., 0.8, 0.1),
+            LightPhase::Red => Color::rgb(0.95, 0.2, 0.2),
+        }
+    }
+}
+
+/// The light cars see at the crosswalks, which all switch together. The pedestrian light
+/// shows walk while it is red.
+#[derive(Resource)]
+pub struct TrafficLight {
+    pub phase: LightPhase,
+    timer: Timer,
+}
+
+impl TrafficLight {
+    pub fn new() -> Self {
+        Self {
+            phase: LightPhase::Green,
+            timer: Timer::from_seconds(LightPhase::Green.duration(), TimerMode::Once),
+        }
+    }
+
+    pub fn cars_stop(&self) -> bool {
+        self.phase != LightPhase::Green
+    }
+}
+
+#[derive(Component)]
+pub struct CarLamp(LightPhase);
+
+#[derive(Component)]
+pub struct PedestrianLamp;
+
+/// The crosswalk, stop line and signals of the lane at `lane_y`, laid out for traffic
+/// driving in `direction`.
+pub fn spawn_crosswalk(commands: &mut Commands, lane_y: f32, direction: f32) {
+    let stripe_color = Color::rgb(COLOR_STRIPE.0, COLOR_STRIPE.1, COLOR_STRIPE.2);
+
+    let mut stripe_y = lane_y - CROSSWALK_HALF_HEIGHT + STRIPE_HEIGHT / 2.;
+    while stripe_y < lane_y + CROSSWALK_HALF_HEIGHT {
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: stripe_color,
+                    custom_size: Some(Vec2::new(CROSSWALK_HALF_WIDTH * 2., STRIPE_HEIGHT)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(CROSSWALK_X * direction, stripe_y, 0.6),
+                ..default()
+            })
+            .insert(GameBackground)
+            .insert(Name::new("Crosswalk"));
+
+        stripe_y += STRIPE_HEIGHT * 2.;
+    }
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: stripe_color,
+                custom_size: Some(Vec2::new(6., CROSSWALK_HALF_HEIGHT * 2.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(STOP_LINE_X * direction, lane_y, 0.6),
+            ..default()
+        })
+        .insert(GameBackground)
+        .insert(Name::new("StopLine"));
+
+    // Car signal above the lane by the stop line, red on top.
+    let signal_x = (STOP_LINE_X - LAMP_SIZE) * direction;
+    let signal_top = lane_y + CROSSWALK_HALF_HEIGHT + LAMP_SIZE * 3.5;
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(COLOR_POLE.0, COLOR_POLE.1, COLOR_POLE.2),
+                custom_size: Some(Vec2::new(LAMP_SIZE * 1.6, LAMP_SIZE * 4.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(signal_x, signal_top - LAMP_SIZE * 1.25, 1.5),
+            ..default()
+        })
+        .insert(GameBackground)
+        .insert(Name::new("TrafficLight"));
+
+    for (slot, phase) in [LightPhase::Red, LightPhase::Yellow, LightPhase::Green]
+        .into_iter()
+        .enumerate()
+    {
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(COLOR_LAMP_OFF.0, COLOR_LAMP_OFF.1, COLOR_LAMP_OFF.2),
+                    custom_size: Some(Vec2::splat(LAMP_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    signal_x,
+                    signal_top - slot as f32 * LAMP_SIZE * 1.25,
+                    1.6,
+                ),
+                ..default()
+            })
+            .insert(CarLamp(phase))
+            .insert(GameBackground)
+            .insert(Name::new("CarLamp"));
+    }
+
+    // Pedestrian signal on the kerb below the crosswalk, where the chicken waits.
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: LightPhase::Red.color(),
+                custom_size: Some(Vec2::new(LAMP_SIZE * 1.6, LAMP_SIZE * 1.6)),
+                ..default()
+            },
+            transform: Transform::from_xyz(
+                (CROSSWALK_X + CROSSWALK_HALF_WIDTH + LAMP_SIZE * 2.) * direction,
+                lane_y - CROSSWALK_HALF_HEIGHT - LAMP_SIZE,
+                1.6,
+            ),
+            ..default()
+        })
+        .insert(PedestrianLamp)
+        .insert(GameBackground)
+        .insert(Name::new("PedestrianLamp"));
+}
+
+pub fn traffic_light_system(
+    mut light: ResMut<TrafficLight>,
+    traffic: Res<TrafficModifiers>,
+    mut car_lamp_query: Query<(&CarLamp, &mut Sprite), Without<PedestrianLamp>>,
+    mut pedestrian_lamp_query: Query<&mut Sprite, With<PedestrianLamp>>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    // Fixed step, and in step with the cars it controls.
+    light
+        .timer
+        .tick(Duration::from_secs_f32(TIME_STEP * traffic.speed.value()));
+
+    if light.timer.finished() {
+        light.phase = light.phase.next();
+        light.timer = Timer::from_seconds(light.phase.duration(), TimerMode::Once);
+    }
+
+    for (lamp, mut sprite) in car_lamp_query.iter_mut() {
+        sprite.color = if lamp.0 == light.phase {
+            lamp.0.color()
+        } else {
+            Color::rgb(COLOR_LAMP_OFF.0, COLOR_LAMP_OFF.1, COLOR_LAMP_OFF.2)
+        };
+    }
+
+    for mut sprite in pedestrian_lamp_query.iter_mut() {
+        sprite.color = if light.phase == LightPhase::Red {
+            LightPhase::Green.color()
+        } else {
+            LightPhase::Red.color()
+        };
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    enemy::TrafficModifiers,
+    game_over::setup_game_over_ui_system,
+    in_game::{in_game_setup_system, GameBackground},
+    player::{GameMode, Player, PlayerModifiers, Session},
+    GameAssets, GameState, COLOR_RED, SIDE_WALK, WIN_HEIGHT,
+};
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+const DAILY_FILE: &str = "chicken_road_daily.txt";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// Keeps the daily road apart from runs that happen to be seeded with a small number.
+const DAILY_SEED_SALT: u64 = 0x5eed_da11_0c0f_fee5;
+const MODIFIERS_PER_DAY: usize = 2;
+const DAILY_SOURCE: &str = "daily";
+
+/// Twists applied on top of the normal rules for a whole day.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DailyModifier {
+    RushHour,
+    Speeding,
+    SundayDrivers,
+    QuickFeet,
+    HeavyFeet,
+}
+
+const DAILY_MODIFIERS: [DailyModifier; 5] = [
+    DailyModifier::RushHour,
+    DailyModifier::Speeding,
+    DailyModifier::SundayDrivers,
+    DailyModifier::QuickFeet,
+    DailyModifier::HeavyFeet,
+];
+
+impl DailyModifier {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DailyModifier::RushHour => "Rush hour",
+            DailyModifier::Speeding => "Speeding",
+            DailyModifier::SundayDrivers => "Sunday drivers",
+            DailyModifier::QuickFeet => "Quick feet",
+            DailyModifier::HeavyFeet => "Heavy feet",
+        }
+    }
+
+    fn traffic_speed(&self) -> f32 {
+        match self {
+            DailyModifier::Speeding => 1.25,
+            DailyModifier::SundayDrivers => 0.75,
+            _ => 1.,
+        }
+    }
+
+    fn spawn_rate(&self) -> f32 {
+        match self {
+            DailyModifier::RushHour => 1.5,
+            _ => 1.,
+        }
+    }
+
+    fn player_speed(&self) -> f32 {
+        match self {
+            DailyModifier::QuickFeet => 1.2,
+            DailyModifier::HeavyFeet => 0.8,
+            _ => 1.,
+        }
+    }
+}
+
+/// Days since the Unix epoch, in UTC so everyone shares the same road whatever their
+/// time zone.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() / SECS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// `YYYY-MM-DD` for a day counted from the Unix epoch.
+pub fn date_label(day: u64) -> String {
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The road and the twists of one day. Everything is derived from the date alone.
+#[derive(Resource)]
+pub struct DailyChallenge {
+    pub day: u64,
+    pub seed: u64,
+    pub modifiers: Vec<DailyModifier>,
+    /// Whether the run in progress is the day's scored attempt.
+    pub scored: bool,
+}
+
+impl DailyChallenge {
+    pub fn for_day(day: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(day ^ DAILY_SEED_SALT);
+        let seed = rng.gen();
+        let modifiers = DAILY_MODIFIERS
+            .choose_multiple(&mut rng, MODIFIERS_PER_DAY)
+            .copied()
+            .collect();
+
+        Self {
+            day,
+            seed,
+            modifiers,
+            scored: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DailyResult {
+    pub level: u8,
+    pub corn: u32,
+}
+
+/// The scored attempt of every day played, kept in a file of its own.
+#[derive(Resource, Default)]
+pub struct DailyResults {
+    pub days: HashMap<u64, DailyResult>,
+}
+
+impl DailyResults {
+    pub fn load() -> Self {
+        match fs::read_to_string(DAILY_FILE) {
+            Ok(contents) => Self::parse(&contents),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = fs::write(DAILY_FILE, self.serialize()) {
+            warn!("could not save daily results: {}", err);
+        }
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut results = Self::default();
+
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let day = fields.next().and_then(|field| field.parse().ok());
+            let level = fields.next().and_then(|field| field.parse().ok());
+            let corn = fields.next().and_then(|field| field.parse().ok());
+
+            if let (Some(day), Some(level), Some(corn)) = (day, level, corn) {
+                results.days.insert(day, DailyResult { level, corn });
+            }
+        }
+
+        results
+    }
+
+    fn serialize(&self) -> String {
+        let mut days: Vec<_> = self.days.iter().collect();
+        days.sort_by_key(|(day, _)| **day);
+
+        days.iter()
+            .map(|(day, result)| {
+                format!(
+                    "{} {} {} {}\n",
+                    day,
+                    result.level,
+                    result.corn,
+                    date_label(**day)
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Component)]
+struct DailyLabel;
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyResults::load())
+            .insert_resource(DailyChallenge::for_day(today()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(daily_setup_system.before(in_game_setup_system)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(apply_daily_modifiers_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(save_daily_result_system.before(setup_game_over_ui_system)),
+            );
+    }
+}
+
+fn daily_setup_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    session: Res<Session>,
+    mut results: ResMut<DailyResults>,
+    mut challenge: ResMut<DailyChallenge>,
+) {
+    if session.game_mode != GameMode::Daily {
+        return;
+    }
+
+    // A game left running past midnight plays the new day's road next time.
+    let day = today();
+    if challenge.day != day {
+        *challenge = DailyChallenge::for_day(day);
+    }
+    challenge.scored = !results.days.contains_key(&day);
+
+    // The attempt counts from the moment it starts, so quitting a bad run doesn't earn
+    // another go.
+    if challenge.scored {
+        results.days.insert(day, DailyResult { level: 1, corn: 0 });
+        results.save();
+    }
+
+    let modifiers: Vec<&str> = challenge
+        .modifiers
+        .iter()
+        .map(|modifier| modifier.label())
+        .collect();
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                format!("{}: {}", date_label(day), modifiers.join(", ")),
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 20.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ),
+            transform: Transform::from_xyz(0., (WIN_HEIGHT + SIDE_WALK) / 2., 3.),
+            ..default()
+        })
+        .insert(DailyLabel)
+        .insert(GameBackground)
+        .insert(Name::new("DailyLabel"));
+}
+
+fn apply_daily_modifiers_system(
+    session: Res<Session>,
+    challenge: Res<DailyChallenge>,
+    mut traffic: ResMut<TrafficModifiers>,
+    mut player_query: Query<&mut PlayerModifiers, With<Player>>,
+) {
+    if session.game_mode != GameMode::Daily {
+        return;
+    }
+
+    let product = |factor: fn(&DailyModifier) -> f32| -> f32 {
+        challenge.modifiers.iter().map(factor).product()
+    };
+
+    traffic
+        .speed
+        .set(DAILY_SOURCE, product(DailyModifier::traffic_speed));
+    traffic
+        .spawn_rate
+        .set(DAILY_SOURCE, product(DailyModifier::spawn_rate));
+
+    for mut modifiers in player_query.iter_mut() {
+        modifiers
+            .speed
+            .set(DAILY_SOURCE, product(DailyModifier::player_speed));
+    }
+}
+
+fn save_daily_result_system(
+    session: Res<Session>,
+    challenge: Res<DailyChallenge>,
+    mut results: ResMut<DailyResults>,
+) {
+    if session.game_mode != GameMode::Daily || !challenge.scored {
+        return;
+    }
+
+    results.days.insert(
+        challenge.day,
+        DailyResult {
+            level: session.level,
+            corn: session.corn,
+        },
+    );
+    results.save();
+}
@@ -0,0 +1,132 @@
+use crate::{
+    animation::{AnimationFinished, Animator, Clip},
+    particles::{Burst, EmitParticles},
+    player::{Player, PlayerState},
+    transition::{Effect, Transitions},
+    GameState,
+};
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+/// The dead chicken is flattened to this scale.
+const SQUASH: Vec2 = Vec2::new(1.4, 0.6);
+
+const SHAKE_SECS: f32 = 0.35;
+const SHAKE_AMPLITUDE: f32 = 7.;
+
+#[derive(Resource)]
+struct DeathSequence {
+    shake: Timer,
+    /// Where the camera was before it started shaking.
+    camera: Vec3,
+}
+
+pub struct DyingPlugin;
+
+impl Plugin for DyingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Dying).with_system(dying_setup_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Dying)
+                    .with_system(screen_shake_system)
+                    .with_system(end_of_death_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Dying).with_system(despawn_dying_system));
+    }
+}
+
+/// Freezes the game, with everything on screen left where it is, as soon as a chicken has
+/// lost its last life.
+pub fn player_death_system(
+    player_query: Query<&PlayerState, With<Player>>,
+    transitions: Res<Transitions>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    // Already on the way out, to the game over screen or back to the editor.
+    if transitions.is_running() {
+        return;
+    }
+
+    if player_query.iter().any(|player_state| !player_state.alive) {
+        game_state.push(GameState::Dying).unwrap();
+    }
+}
+
+fn dying_setup_system(
+    mut commands: Commands,
+    mut player_query: Query<(&mut Transform, &PlayerState, &mut Animator), With<Player>>,
+    camera_query: Query<&Transform, (With<Camera2d>, Without<Player>)>,
+    mut emit: EventWriter<EmitParticles>,
+) {
+    for (mut player_tf, player_state, mut animator) in player_query.iter_mut() {
+        if player_state.alive {
+            animator.play(Clip::Idle);
+            continue;
+        }
+
+        player_tf.scale.x *= SQUASH.x;
+        player_tf.scale.y *= SQUASH.y;
+
+        emit.send(EmitParticles {
+            burst: Burst::Feathers,
+            position: player_tf.translation.truncate(),
+        });
+    }
+
+    commands.insert_resource(DeathSequence {
+        shake: Timer::from_seconds(SHAKE_SECS, TimerMode::Once),
+        camera: camera_query
+            .get_single()
+            .map_or(Vec3::ZERO, |camera_tf| camera_tf.translation),
+    });
+}
+
+fn screen_shake_system(
+    mut sequence: ResMut<DeathSequence>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    time: Res<Time>,
+) {
+    let Ok(mut camera_tf) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    sequence.shake.tick(time.delta());
+
+    let amplitude = SHAKE_AMPLITUDE * sequence.shake.percent_left();
+    let mut rng = thread_rng();
+    let offset = Vec2::new(rng.gen_range(-1.0..=1.), rng.gen_range(-1.0..=1.)) * amplitude;
+
+    camera_tf.translation = sequence.camera + offset.extend(0.);
+}
+
+/// Closes in on the chicken once its death animation has played, then shows the game over
+/// screen.
+fn end_of_death_system(
+    mut finished: EventReader<AnimationFinished>,
+    player_query: Query<&PlayerState>,
+    mut transitions: ResMut<Transitions>,
+) {
+    let died = finished.iter().any(|event| {
+        event.clip == Clip::Death
+            && player_query
+                .get(event.entity)
+                .is_ok_and(|player_state| !player_state.alive)
+    });
+
+    if died {
+        // Unwinds the frozen game underneath as well.
+        transitions.replace(GameState::GameOver, Effect::Iris);
+    }
+}
+
+fn despawn_dying_system(
+    mut commands: Commands,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    sequence: Res<DeathSequence>,
+) {
+    if let Ok(mut camera_tf) = camera_query.get_single_mut() {
+        camera_tf.translation = sequence.camera;
+    }
+
+    commands.remove_resource::<DeathSequence>();
+}
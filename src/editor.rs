@@ -0,0 +1,411 @@
+use crate::{
+    enemy::{ENEMY_DIM, ENEMY_SCALE},
+    level::{
+        spawn_obstacle, Lane, LaneKind, Level, LevelHandle, LevelObstacle, ObstacleKind,
+        LANE_MIN_GAP,
+    },
+    player::{GameMode, PlayerMode, Session},
+    river::RIVER_HALF_HEIGHT,
+    tilemap::spawn_tilemap,
+    transition::{Effect, Transitions},
+    GameAssets, GameState, COLOR_RED, COLOR_YELLOW, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::prelude::*;
+
+const SPAWN_RATE_STEP: f32 = 0.25;
+const SPAWN_RATE_MAX: f32 = 4.;
+const RED_SHARE_STEP: f32 = 0.1;
+/// Lanes and obstacles snap to this grid.
+const GRID: f32 = 10.;
+const LANE_ALPHA: f32 = 0.35;
+
+const HELP: &str = "Click: select lane   A: add lane   Del: remove   T: type   D: direction\n\
+    +/-: spawn rate   [/]: red cars   Right click: place/remove obstacle   O: obstacle type\n\
+    Enter: test   S: save   Esc: menu";
+
+/// What the editor is working on, kept across test plays.
+#[derive(Resource)]
+pub struct EditorCursor {
+    /// Height of the selected lane.
+    selected: Option<f32>,
+    /// Kind of obstacle placed by a right click.
+    brush: ObstacleKind,
+    /// Edits made since the level was last saved.
+    dirty: bool,
+}
+
+impl Default for EditorCursor {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            brush: ObstacleKind::Hydrant,
+            dirty: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct EditorBackground;
+
+/// The lanes and obstacles drawn by the editor, rebuilt whenever the level changes.
+#[derive(Component)]
+struct EditorView;
+
+#[derive(Component)]
+struct EditorStatus;
+
+type EditorEntities = Or<(With<EditorBackground>, With<EditorView>)>;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EditorCursor::default())
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_editor_system))
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(editor_setup_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .with_system(editor_input_system)
+                    .with_system(editor_view_system.after(editor_input_system))
+                    .with_system(editor_status_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Editor).with_system(despawn_editor_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(end_test_play_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(end_test_play_system),
+            );
+    }
+}
+
+fn open_editor_system(kb: Res<Input<KeyCode>>, mut transitions: ResMut<Transitions>) {
+    if kb.just_pressed(KeyCode::E) {
+        transitions.set(GameState::Editor, Effect::Fade);
+    }
+}
+
+/// Where the mouse points on the road, if it is over the window.
+fn cursor_position(windows: &Windows) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    Some(cursor - Vec2::new(window.width(), window.height()) / 2.)
+}
+
+fn snap(value: f32) -> f32 {
+    (value / GRID).round() * GRID
+}
+
+fn lane_height(kind: LaneKind) -> f32 {
+    match kind {
+        LaneKind::River => RIVER_HALF_HEIGHT * 2.,
+        _ => ENEMY_DIM.1 * ENEMY_SCALE,
+    }
+}
+
+fn lane_color(kind: LaneKind) -> Color {
+    let (r, g, b) = match kind {
+        LaneKind::Road => (0.25, 0.25, 0.28),
+        LaneKind::Crosswalk => (0.55, 0.55, 0.6),
+        LaneKind::River => (0.2, 0.45, 0.8),
+        LaneKind::Rail => (0.45, 0.3, 0.2),
+    };
+    Color::rgba(r, g, b, LANE_ALPHA)
+}
+
+fn lane_label(lane: &Lane) -> String {
+    let arrow = if lane.direction < 0. { "<<" } else { ">>" };
+
+    if lane.kind.carries_cars() {
+        format!(
+            "{} {}  rate x{:.2}  red {:.0}%",
+            lane.kind.key().to_uppercase(),
+            arrow,
+            lane.spawn_rate,
+            lane.red_share * 100.
+        )
+    } else {
+        format!("{} {}", lane.kind.key().to_uppercase(), arrow)
+    }
+}
+
+fn editor_setup_system(mut commands: Commands, game_assets: Res<GameAssets>) {
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                HELP,
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 16.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_xyz(0., -(WIN_HEIGHT + SIDE_WALK) / 2., 3.),
+            ..default()
+        })
+        .insert(EditorBackground)
+        .insert(Name::new("EditorHelp"));
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 30.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ),
+            transform: Transform::from_xyz(0., (WIN_HEIGHT + SIDE_WALK) / 2., 3.),
+            ..default()
+        })
+        .insert(EditorStatus)
+        .insert(EditorBackground)
+        .insert(Name::new("EditorStatus"));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_input_system(
+    kb: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    mut level: ResMut<Level>,
+    level_handle: Res<LevelHandle>,
+    mut saved_levels: ResMut<Assets<Level>>,
+    mut cursor: ResMut<EditorCursor>,
+    mut session: ResMut<Session>,
+    mut transitions: ResMut<Transitions>,
+) {
+    if kb.just_pressed(KeyCode::Escape) {
+        // Leaving throws away whatever was not saved.
+        *level = saved_levels
+            .get(&level_handle.0)
+            .cloned()
+            .unwrap_or_default();
+        *cursor = EditorCursor::default();
+        transitions.set(GameState::Menu, Effect::Fade);
+        return;
+    }
+
+    if kb.just_pressed(KeyCode::Return) {
+        session.mode = PlayerMode::Single;
+        session.game_mode = GameMode::Classic;
+        session.test_play = true;
+        session.ghost_race = false;
+        transitions.set(GameState::InGame, Effect::Wipe);
+        return;
+    }
+
+    if kb.just_pressed(KeyCode::S) {
+        level.save();
+        // The asset server doesn't watch the file, so the loaded level is updated by hand.
+        saved_levels.set_untracked(&level_handle.0, level.clone());
+        cursor.dirty = false;
+        return;
+    }
+
+    let pointer = cursor_position(&windows);
+
+    if mouse.just_pressed(MouseButton::Left) {
+        if let Some(pointer) = pointer {
+            cursor.selected = level
+                .lanes
+                .iter()
+                .find(|lane| (lane.y - pointer.y).abs() < LANE_MIN_GAP / 2.)
+                .map(|lane| lane.y);
+        }
+    }
+
+    if mouse.just_pressed(MouseButton::Right) {
+        if let Some(pointer) = pointer {
+            let existing = level
+                .obstacles
+                .iter()
+                .position(|obstacle| obstacle.contains(pointer));
+
+            match existing {
+                Some(index) => {
+                    level.obstacles.remove(index);
+                }
+                None => level.obstacles.push(LevelObstacle::new(
+                    cursor.brush,
+                    snap(pointer.x),
+                    snap(pointer.y),
+                )),
+            }
+            cursor.dirty = true;
+        }
+    }
+
+    if kb.just_pressed(KeyCode::O) {
+        cursor.brush = cursor.brush.next();
+    }
+
+    if kb.just_pressed(KeyCode::A) {
+        if let Some(y) = pointer.map(|pointer| snap(pointer.y)) {
+            if level.has_room(y) {
+                level.add_lane(Lane::new(y, LaneKind::Road));
+                cursor.selected = Some(y);
+                cursor.dirty = true;
+            }
+        }
+    }
+
+    let Some(selected_y) = cursor.selected else {
+        return;
+    };
+
+    if kb.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        level.lanes.retain(|lane| (lane.y - selected_y).abs() >= 1.);
+        cursor.selected = None;
+        cursor.dirty = true;
+        return;
+    }
+
+    // Edits go to a copy so the level only counts as changed, and is redrawn, when a key
+    // actually did something.
+    let Some(index) = level
+        .lanes
+        .iter()
+        .position(|lane| (lane.y - selected_y).abs() < 1.)
+    else {
+        return;
+    };
+    let mut lane = level.lanes[index];
+
+    if kb.just_pressed(KeyCode::T) {
+        lane.kind = lane.kind.next();
+    }
+    if kb.just_pressed(KeyCode::D) {
+        lane.direction = -lane.direction;
+    }
+    if kb.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        lane.spawn_rate = (lane.spawn_rate + SPAWN_RATE_STEP).min(SPAWN_RATE_MAX);
+    }
+    if kb.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        lane.spawn_rate = (lane.spawn_rate - SPAWN_RATE_STEP).max(0.);
+    }
+    if kb.just_pressed(KeyCode::RBracket) {
+        lane.red_share = (lane.red_share + RED_SHARE_STEP).min(1.);
+    }
+    if kb.just_pressed(KeyCode::LBracket) {
+        lane.red_share = (lane.red_share - RED_SHARE_STEP).max(0.);
+    }
+
+    if lane != level.lanes[index] {
+        level.lanes[index] = lane;
+        cursor.dirty = true;
+    }
+}
+
+/// Draws the lanes and obstacles of the level over the road.
+fn editor_view_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    level: Res<Level>,
+    cursor: Res<EditorCursor>,
+    view_query: Query<Entity, With<EditorView>>,
+) {
+    if !level.is_changed() && !cursor.is_changed() {
+        return;
+    }
+
+    for ent in view_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    for ent in spawn_tilemap(&mut commands, &level) {
+        commands.entity(ent).insert(EditorView);
+    }
+
+    for lane in level.lanes.iter() {
+        let height = lane_height(lane.kind);
+
+        if cursor.selected == Some(lane.y) {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+                        custom_size: Some(Vec2::new(WIN_WIDTH, height + 8.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., lane.y, 0.9),
+                    ..default()
+                })
+                .insert(EditorView);
+        }
+
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: lane_color(lane.kind),
+                    custom_size: Some(Vec2::new(WIN_WIDTH, height)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., lane.y, 1.),
+                ..default()
+            })
+            .insert(EditorView)
+            .insert(Name::new("EditorLane"))
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        lane_label(lane),
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 22.,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    transform: Transform::from_xyz(0., 0., 1.),
+                    ..default()
+                });
+            });
+    }
+
+    for obstacle in level.obstacles.iter() {
+        let ent = spawn_obstacle(&mut commands, obstacle);
+        commands.entity(ent).insert(EditorView);
+    }
+}
+
+fn editor_status_system(
+    cursor: Res<EditorCursor>,
+    mut status_query: Query<&mut Text, With<EditorStatus>>,
+) {
+    let Ok(mut status) = status_query.get_single_mut() else {
+        return;
+    };
+
+    let brush = cursor.brush.key().to_uppercase();
+    status.sections[0].value = if cursor.dirty {
+        format!("ROAD EDITOR - {} - unsaved changes", brush)
+    } else {
+        format!("ROAD EDITOR - {}", brush)
+    };
+}
+
+fn despawn_editor_system(mut commands: Commands, editor_query: Query<Entity, EditorEntities>) {
+    for ent in editor_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+/// Back to the editor when a test play ends, or on Escape.
+fn end_test_play_system(
+    kb: Res<Input<KeyCode>>,
+    session: Res<Session>,
+    game_state: Res<State<GameState>>,
+    mut transitions: ResMut<Transitions>,
+) {
+    if !session.test_play {
+        return;
+    }
+
+    if *game_state.current() == GameState::GameOver || kb.just_pressed(KeyCode::Escape) {
+        transitions.set(GameState::Editor, Effect::Fade);
+    }
+}
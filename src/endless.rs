@@ -0,0 +1,547 @@
+use crate::{
+    animation::Animator,
+    dying::player_death_system,
+    enemy::{
+        enemy_hit_player_system, signal_color, signal_lamp, signal_phase, spawn_car, spawn_train,
+        Enemy, EnemyVelocity, TrafficModifiers, CAR_SPEED_RANGE, ENEMY_DIM, ENEMY_SCALE,
+        RAIL_GAUGE, SIGNAL_LAMP_SIZE, TRAIN_INTERVAL, TRAIN_SPEED, TRAIN_WARNING_SECS,
+    },
+    mutators::MutatorSet,
+    player::{
+        invulnerable_system, lose_life, move_player_system, player_input_system, spawn_players,
+        PlayArea, Player, PlayerState, Session,
+    },
+    river::{
+        fill_river_lane, move_platform_system, platform_under, platforms_in, ride_platform,
+        river_lane_at, spawn_next_platform, turtle_dive_system, Platform, Turtle,
+        RIVER_HALF_HEIGHT, RIVER_SPEED,
+    },
+    tilemap::{COLOR_ASPHALT, COLOR_BALLAST, COLOR_GRASS, COLOR_RAIL, COLOR_WATER, RAIL_HEIGHT},
+    GameAssets, GameState, BASE_SPEED, PLAYER_DIM, SIDE_WALK, TIME_STEP, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SCREEN_HEIGHT: f32 = WIN_HEIGHT + SIDE_WALK * 2.;
+const LANE_HEIGHT: f32 = 160.;
+/// Lanes at the bottom of the world that are always safe, so the run starts on grass.
+const START_LANES: u32 = 2;
+
+/// Relative odds of each kind of lane being generated.
+const LANE_WEIGHTS: [(LaneKind, u32); 4] = [
+    (LaneKind::Grass, 2),
+    (LaneKind::Road, 4),
+    (LaneKind::River, 2),
+    (LaneKind::Rail, 1),
+];
+
+const CAR_GAP: (f32, f32) = (120., 360.);
+/// Extra traffic speed gained with every lane, up to `MAX_DIFFICULTY`.
+const DIFFICULTY_PER_LANE: f32 = 0.015;
+const MAX_DIFFICULTY: f32 = 1.;
+
+/// How quickly the camera catches up with a chicken running ahead.
+const CAMERA_FOLLOW_RATE: f32 = 3.;
+/// Pixels per second the camera scrolls on its own once the run is under way.
+const CAMERA_CREEP: f32 = 12.;
+/// How far past the edge of the screen traffic is kept before being despawned.
+const OFFSCREEN_MARGIN: f32 = 200.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LaneKind {
+    Grass,
+    Road,
+    River,
+    Rail,
+}
+
+impl LaneKind {
+    fn color(&self) -> Color {
+        let (r, g, b) = match self {
+            LaneKind::Grass | LaneKind::River => COLOR_GRASS,
+            LaneKind::Road => COLOR_ASPHALT,
+            LaneKind::Rail => COLOR_BALLAST,
+        };
+        Color::rgb(r, g, b)
+    }
+}
+
+/// One generated strip of the world, carrying its background sprite.
+#[derive(Component)]
+struct EndlessLane {
+    kind: LaneKind,
+    /// 1 when traffic in the lane heads right, -1 when it heads left.
+    direction: f32,
+    speed: f32,
+    /// Seconds until the next car, platform or train enters the screen.
+    next_in: f32,
+}
+
+impl EndlessLane {
+    fn entry_x(&self) -> f32 {
+        -self.direction * WIN_WIDTH / 2.
+    }
+}
+
+/// Cars, trains and platforms that belong to a generated lane.
+type EndlessTraffic = Or<(With<Enemy>, With<Platform>)>;
+
+#[derive(Component)]
+struct RailSignal;
+
+#[derive(Resource)]
+struct EndlessWorld {
+    rng: StdRng,
+    /// Index of the next lane to generate.
+    next_lane: u32,
+}
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Endless)
+                .with_system(endless_setup_system)
+                .with_system(spawn_players),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Endless)
+                .with_system(player_input_system)
+                .with_system(move_player_system)
+                .with_system(scroll_camera_system.after(move_player_system))
+                .with_system(generate_lanes_system.after(scroll_camera_system))
+                .with_system(lane_traffic_system)
+                .with_system(move_traffic_system)
+                .with_system(move_platform_system)
+                .with_system(turtle_dive_system)
+                .with_system(ride_platform_system.before(move_platform_system))
+                .with_system(rail_signal_system)
+                .with_system(enemy_hit_player_system)
+                .with_system(invulnerable_system)
+                .with_system(player_death_system)
+                .with_system(drown_system)
+                .with_system(respawn_row_system.after(scroll_camera_system))
+                .with_system(fall_behind_system.after(respawn_row_system))
+                .with_system(distance_system),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Endless).with_system(despawn_endless_system));
+    }
+}
+
+fn lane_y(index: u32) -> f32 {
+    -SCREEN_HEIGHT / 2. + LANE_HEIGHT * (index as f32 + 0.5)
+}
+
+fn lane_index(y: f32) -> u32 {
+    ((y + SCREEN_HEIGHT / 2.) / LANE_HEIGHT).max(0.) as u32
+}
+
+fn difficulty(index: u32) -> f32 {
+    1. + (index as f32 * DIFFICULTY_PER_LANE).min(MAX_DIFFICULTY)
+}
+
+pub fn endless_setup_system(mut commands: Commands, mut session: ResMut<Session>) {
+    session.reset();
+    session.seed = rand::random();
+    // The menu greys the mutators out for this mode.
+    session.mutators = MutatorSet::default();
+
+    commands.insert_resource(TrafficModifiers::default());
+    commands.insert_resource(EndlessWorld {
+        rng: StdRng::seed_from_u64(session.seed),
+        next_lane: 0,
+    });
+}
+
+fn roll_lane_kind(rng: &mut StdRng) -> LaneKind {
+    let total: u32 = LANE_WEIGHTS.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0..total);
+
+    for (kind, weight) in LANE_WEIGHTS {
+        if roll < weight {
+            return kind;
+        }
+        roll -= weight;
+    }
+
+    LaneKind::Grass
+}
+
+/// Spawns a car whose front is at `front`. Returns its length.
+fn spawn_lane_car(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    rng: &mut StdRng,
+    lane: &EndlessLane,
+    y: f32,
+    front: f32,
+) -> f32 {
+    let length = ENEMY_DIM.0 * ENEMY_SCALE;
+    let position = Vec2::new(front - lane.direction * length / 2., y);
+    spawn_car(
+        commands,
+        game_assets,
+        rng.gen_bool(0.5),
+        position,
+        lane.direction * lane.speed,
+    );
+
+    length
+}
+
+fn spawn_lane(commands: &mut Commands, game_assets: &GameAssets, rng: &mut StdRng, index: u32) {
+    let kind = if index < START_LANES {
+        LaneKind::Grass
+    } else {
+        roll_lane_kind(rng)
+    };
+    let direction = if rng.gen_bool(0.5) { 1. } else { -1. };
+    let speed = difficulty(index)
+        * match kind {
+            LaneKind::Road => rng.gen_range(CAR_SPEED_RANGE.0..CAR_SPEED_RANGE.1),
+            LaneKind::River => RIVER_SPEED,
+            LaneKind::Rail => TRAIN_SPEED,
+            LaneKind::Grass => 0.,
+        };
+    let y = lane_y(index);
+
+    let mut lane = EndlessLane {
+        kind,
+        direction,
+        speed,
+        next_in: 0.,
+    };
+
+    // Fill the lane from the far edge back so it is already busy when it scrolls into view.
+    lane.next_in = match kind {
+        LaneKind::Road => {
+            let mut front = direction * WIN_WIDTH / 2.;
+            while direction * front > -WIN_WIDTH / 2. {
+                let length = spawn_lane_car(commands, game_assets, rng, &lane, y, front);
+                front -= direction * (length + rng.gen_range(CAR_GAP.0..CAR_GAP.1));
+            }
+            (-WIN_WIDTH / 2. - direction * front) / (speed * BASE_SPEED)
+        }
+        LaneKind::River => fill_river_lane(commands, rng, y, direction * speed),
+        LaneKind::Rail => rng.gen_range(0. ..TRAIN_INTERVAL),
+        LaneKind::Grass => 0.,
+    };
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::new(WIN_WIDTH, LANE_HEIGHT)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., y, 0.4),
+            ..default()
+        })
+        .insert(lane)
+        .insert(Name::new("EndlessLane"))
+        .with_children(|parent| match kind {
+            LaneKind::River => {
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(COLOR_WATER.0, COLOR_WATER.1, COLOR_WATER.2),
+                        custom_size: Some(Vec2::new(WIN_WIDTH, RIVER_HALF_HEIGHT * 2.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., 0.1),
+                    ..default()
+                });
+            }
+            LaneKind::Rail => {
+                for rail_y in [-RAIL_GAUGE / 2., RAIL_GAUGE / 2.] {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(COLOR_RAIL.0, COLOR_RAIL.1, COLOR_RAIL.2),
+                            custom_size: Some(Vec2::new(WIN_WIDTH, RAIL_HEIGHT)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0., rail_y, 0.1),
+                        ..default()
+                    });
+                }
+
+                // On the side the trains come from.
+                parent
+                    .spawn(signal_lamp(Vec3::new(
+                        -direction * (WIN_WIDTH / 2. - 30.),
+                        LANE_HEIGHT / 2. - SIGNAL_LAMP_SIZE,
+                        1.,
+                    )))
+                    .insert(RailSignal);
+            }
+            _ => {}
+        });
+}
+
+/// Keeps the world generated a lane past the top of the screen, and drops what has scrolled
+/// off the bottom.
+fn generate_lanes_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut world: ResMut<EndlessWorld>,
+    play_area: Res<PlayArea>,
+    lane_query: Query<(Entity, &Transform), With<EndlessLane>>,
+    traffic_query: Query<(Entity, &Transform), EndlessTraffic>,
+) {
+    let screen_top = play_area.top + PLAYER_DIM;
+    let screen_bottom = play_area.bottom - PLAYER_DIM;
+
+    while lane_y(world.next_lane) - LANE_HEIGHT / 2. < screen_top + LANE_HEIGHT {
+        let index = world.next_lane;
+        spawn_lane(&mut commands, &game_assets, &mut world.rng, index);
+        world.next_lane += 1;
+    }
+
+    for (ent, transform) in lane_query.iter() {
+        if transform.translation.y + LANE_HEIGHT / 2. < screen_bottom {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+
+    for (ent, transform) in traffic_query.iter() {
+        if transform.translation.y + LANE_HEIGHT / 2. < screen_bottom {
+            commands.entity(ent).despawn();
+        }
+    }
+}
+
+fn lane_traffic_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut world: ResMut<EndlessWorld>,
+    traffic: Res<TrafficModifiers>,
+    mut lane_query: Query<(&mut EndlessLane, &Transform)>,
+) {
+    let dt = TIME_STEP * traffic.speed.value();
+
+    for (mut lane, lane_tf) in lane_query.iter_mut() {
+        if lane.kind == LaneKind::Grass {
+            continue;
+        }
+
+        lane.next_in -= dt;
+        if lane.next_in > 0. {
+            continue;
+        }
+
+        let front = lane.entry_x();
+        let y = lane_tf.translation.y;
+        let velocity_x = lane.direction * lane.speed;
+        let rng = &mut world.rng;
+
+        lane.next_in += match lane.kind {
+            LaneKind::Road => {
+                let length = spawn_lane_car(&mut commands, &game_assets, rng, &lane, y, front);
+                (length + rng.gen_range(CAR_GAP.0..CAR_GAP.1)) / (lane.speed * BASE_SPEED)
+            }
+            LaneKind::River => spawn_next_platform(&mut commands, rng, y, velocity_x),
+            LaneKind::Rail => {
+                spawn_train(&mut commands, Vec2::new(front, y), velocity_x);
+                TRAIN_INTERVAL
+            }
+            LaneKind::Grass => 0.,
+        };
+    }
+}
+
+fn move_traffic_system(
+    mut commands: Commands,
+    traffic: Res<TrafficModifiers>,
+    mut enemy_query: Query<(Entity, &mut Transform, &EnemyVelocity)>,
+) {
+    let dt = TIME_STEP * traffic.speed.value();
+    let gone =
+        |x: f32, velocity_x: f32| x * velocity_x.signum() > WIN_WIDTH / 2. + OFFSCREEN_MARGIN;
+
+    for (ent, mut transform, velocity) in enemy_query.iter_mut() {
+        transform.translation.x += velocity.x * dt * BASE_SPEED;
+        if gone(transform.translation.x, velocity.x) {
+            commands.entity(ent).despawn();
+        }
+    }
+}
+
+/// The river lane the chicken is standing in, if any.
+fn river_at<'a>(
+    y: f32,
+    lanes: impl Iterator<Item = (&'a EndlessLane, &'a Transform)>,
+) -> Option<f32> {
+    let river_ys = lanes
+        .filter(|(lane, _)| lane.kind == LaneKind::River)
+        .map(|(_, lane_tf)| lane_tf.translation.y);
+
+    river_lane_at(river_ys, y)
+}
+
+fn ride_platform_system(
+    mut player_query: Query<&mut Transform, With<Player>>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>), Without<Player>>,
+    lane_query: Query<(&EndlessLane, &Transform), Without<Player>>,
+    traffic: Res<TrafficModifiers>,
+) {
+    for mut player_tf in player_query.iter_mut() {
+        let Some(river_y) = river_at(player_tf.translation.y, lane_query.iter()) else {
+            continue;
+        };
+
+        ride_platform(
+            &mut player_tf,
+            river_y,
+            platform_query.iter(),
+            traffic.speed.value(),
+        );
+    }
+}
+
+fn drown_system(
+    mut player_query: Query<(&Player, &mut Transform, &mut PlayerState, &mut Animator)>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>), Without<Player>>,
+    lane_query: Query<(&EndlessLane, &Transform), Without<Player>>,
+    mut session: ResMut<Session>,
+    play_area: Res<PlayArea>,
+) {
+    for (player, mut player_tf, mut player_state, mut animator) in player_query.iter_mut() {
+        let Some(river_y) = river_at(player_tf.translation.y, lane_query.iter()) else {
+            continue;
+        };
+
+        if platform_under(&player_tf, platforms_in(river_y, platform_query.iter())).is_some() {
+            continue;
+        }
+
+        if lose_life(
+            player,
+            &mut player_tf,
+            &mut player_state,
+            &mut animator,
+            &mut session,
+            play_area.respawn_y,
+        ) {
+            return;
+        }
+    }
+}
+
+fn rail_signal_system(
+    time: Res<Time>,
+    lane_query: Query<&EndlessLane>,
+    mut signal_query: Query<(&Parent, &mut Sprite), With<RailSignal>>,
+) {
+    let phase = signal_phase(time.elapsed_seconds());
+
+    for (parent, mut sprite) in signal_query.iter_mut() {
+        let Ok(lane) = lane_query.get(parent.get()) else {
+            continue;
+        };
+
+        sprite.color = signal_color(lane.next_in < TRAIN_WARNING_SECS && phase == 0);
+    }
+}
+
+/// Follows the chicken up the world and, once it has left the start, keeps creeping on so
+/// it can't dawdle. The camera never scrolls back down.
+fn scroll_camera_system(
+    session: Res<Session>,
+    mut play_area: ResMut<PlayArea>,
+    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let Ok(mut camera_tf) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let mut camera_y = camera_tf.translation.y;
+
+    if let Ok(player_tf) = player_query.get_single() {
+        let target = player_tf.translation.y + LANE_HEIGHT;
+        if target > camera_y {
+            camera_y += (target - camera_y) * (CAMERA_FOLLOW_RATE * TIME_STEP).min(1.);
+        }
+    }
+
+    if session.score > 0 {
+        camera_y += CAMERA_CREEP * TIME_STEP;
+    }
+
+    camera_tf.translation.y = camera_y;
+    play_area.bottom = camera_y - SCREEN_HEIGHT / 2. + PLAYER_DIM;
+    play_area.top = camera_y + SCREEN_HEIGHT / 2. - PLAYER_DIM;
+}
+
+/// Chickens losing a life come back on the lowest grass still well on screen, or failing that
+/// the lowest lane they can stand in, rather than at the start the camera has left behind.
+fn respawn_row_system(
+    mut play_area: ResMut<PlayArea>,
+    lane_query: Query<(&EndlessLane, &Transform), Without<Player>>,
+) {
+    let lowest = play_area.bottom + LANE_HEIGHT / 2.;
+
+    play_area.respawn_y = lane_query
+        .iter()
+        .filter(|(lane, lane_tf)| lane.kind != LaneKind::River && lane_tf.translation.y >= lowest)
+        .min_by(|(lane, lane_tf), (other, other_tf)| {
+            (lane.kind != LaneKind::Grass)
+                .cmp(&(other.kind != LaneKind::Grass))
+                .then(lane_tf.translation.y.total_cmp(&other_tf.translation.y))
+        })
+        .map_or(lowest, |(_, lane_tf)| lane_tf.translation.y);
+}
+
+/// A chicken left behind by the camera is lost.
+fn fall_behind_system(
+    mut player_query: Query<(&Player, &mut Transform, &mut PlayerState, &mut Animator)>,
+    play_area: Res<PlayArea>,
+    mut session: ResMut<Session>,
+) {
+    for (player, mut player_tf, mut player_state, mut animator) in player_query.iter_mut() {
+        if player_tf.translation.y >= play_area.bottom - PLAYER_DIM {
+            continue;
+        }
+
+        if lose_life(
+            player,
+            &mut player_tf,
+            &mut player_state,
+            &mut animator,
+            &mut session,
+            play_area.respawn_y,
+        ) {
+            return;
+        }
+    }
+}
+
+fn distance_system(mut session: ResMut<Session>, player_query: Query<&Transform, With<Player>>) {
+    for player_tf in player_query.iter() {
+        session.score = session.score.max(lane_index(player_tf.translation.y));
+    }
+}
+
+fn despawn_endless_system(
+    mut commands: Commands,
+    lane_query: Query<Entity, With<EndlessLane>>,
+    traffic_query: Query<Entity, EndlessTraffic>,
+    player_query: Query<Entity, With<Player>>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    mut play_area: ResMut<PlayArea>,
+) {
+    for ent in lane_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    for ent in traffic_query.iter().chain(player_query.iter()) {
+        commands.entity(ent).despawn();
+    }
+
+    for mut camera_tf in camera_query.iter_mut() {
+        camera_tf.translation.y = 0.;
+    }
+
+    *play_area = PlayArea::default();
+    commands.remove_resource::<EndlessWorld>();
+}
@@ -1,17 +1,57 @@
+use std::time::Duration;
+
 use crate::{
-    player::{Player, PlayerState},
-    GameAssets, GameState, BASE_SPEED, PLAYER_DIM, TIME_STEP, WIN_WIDTH,
+    animation::{Animator, Clip},
+    crosswalk::{TrafficLight, STOP_LINE_X},
+    fairness::{route_exists, Crossing},
+    in_game::GameBackground,
+    level::{LaneKind, Level},
+    modifiers::Scale,
+    net::Lockstep,
+    particles::{Burst, EmitParticles, ParticleEmitter},
+    player::{
+        lose_life, Invulnerable, PlayArea, Player, PlayerModifiers, PlayerState, Session, Sidewalk,
+        PLAYER_START_Y,
+    },
+    powerup::ActiveEffects,
+    GameAssets, BASE_SPEED, COLOR_RED, PLAYER_DIM, TIME_STEP, WIN_WIDTH,
 };
+use bevy::ecs::system::EntityCommands;
 use bevy::math::Vec3Swizzles;
 use bevy::{prelude::*, sprite::collide_aabb::collide};
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub const ENEMY_DIM: (f32, f32) = (48., 24.);
+pub const ENEMY_SCALE: f32 = 2.5;
+
+/// Range of cruising speeds; every car picks its own so traffic is mixed.
+pub const CAR_SPEED_RANGE: (f32, f32) = (0.7, 1.3);
+/// Speed units gained per second when pulling away.
+const CAR_ACCEL: f32 = 1.2;
+/// Speed units shed per second when braking for a car ahead or a red light.
+const CAR_BRAKE: f32 = 3.;
+const CAR_MIN_GAP: f32 = 16.;
+/// Extra following distance per unit of speed, as seconds of travel.
+const CAR_HEADWAY_SECS: f32 = 0.3;
 
-const FIRST_LANE: f32 = -210.;
-const SECOND_LANE: f32 = 0.;
-const THIRD_LANE: f32 = 198.;
+/// Shortest time between two cars, however high the level gets.
+const MIN_SPAWN_INTERVAL: f32 = 0.2;
 
-const ENEMY_DIM: (f32, f32) = (48., 24.);
-const ENEMY_SCALE: f32 = 2.5;
+pub const TRAIN_INTERVAL: f32 = 15.;
+pub const TRAIN_SPEED: f32 = 4.;
+const TRAIN_WAGONS: u32 = 8;
+pub const TRAIN_WARNING_SECS: f32 = 2.;
+/// Cars stop entering the crossing this long before a train, so it has cleared by then.
+const CROSSING_CLOSE_SECS: f32 = 4.5;
+const SIGNAL_BLINK_RATE: f32 = 4.;
+const COLOR_LOCOMOTIVE: (f32, f32, f32) = (0.55, 0.1, 0.12);
+const COLOR_WAGON: (f32, f32, f32) = (0.3, 0.35, 0.45);
+const COLOR_LAMP_OFF: (f32, f32, f32) = (0.25, 0.1, 0.1);
+pub const RAIL_GAUGE: f32 = 36.;
+pub const SIGNAL_LAMP_SIZE: f32 = 14.;
+const SIGNAL_X: f32 = -WIN_WIDTH / 2. + 30.;
+/// Height of the crossing signal above its rail lane.
+const SIGNAL_OFFSET_Y: f32 = 80.;
 
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
@@ -23,94 +63,664 @@ pub struct EnemyVelocity {
     pub x: f32,
 }
 
+/// A car on the road, as opposed to a train. Cars follow the one ahead in their lane.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Vehicle {
+    pub max_speed: f32,
+}
+
 #[derive(Resource, Default)]
 pub struct EnemySpawnConfig {
     pub timer: Timer,
+    /// Set while an online race drives the traffic difficulty for every peer.
+    pub remote_controlled: bool,
+}
+
+impl EnemySpawnConfig {
+    pub fn new() -> Self {
+        Self {
+            timer: Timer::new(Duration::from_secs(2), TimerMode::Repeating),
+            remote_controlled: false,
+        }
+    }
+
+    pub fn set_level(&mut self, level: u8) {
+        let next_time = (2. - f32::from(level) * 0.05).max(MIN_SPAWN_INTERVAL);
+        self.timer.set_duration(Duration::from_secs_f32(next_time));
+    }
+}
+
+/// Fixed timetable shared by the trains of every rail lane. Each cycle starts the moment a train
+/// reaches the crossing.
+#[derive(Resource)]
+pub struct TrainSchedule {
+    pub timer: Timer,
+    dispatched: bool,
+}
+
+impl TrainSchedule {
+    pub fn new() -> Self {
+        let mut timer = Timer::from_seconds(TRAIN_INTERVAL, TimerMode::Repeating);
+        // Start as if the last train had just gone by, not as one passes.
+        timer.set_elapsed(Duration::from_secs_f32(Self::pass_secs()));
+
+        Self {
+            timer,
+            dispatched: false,
+        }
+    }
+
+    /// How long a train takes to clear the screen once its front reaches it.
+    fn pass_secs() -> f32 {
+        (WIN_WIDTH + ENEMY_DIM.0 * ENEMY_SCALE * TRAIN_WAGONS as f32) / (TRAIN_SPEED * BASE_SPEED)
+    }
+
+    pub fn secs_to_arrival(&self) -> f32 {
+        self.timer.remaining_secs()
+    }
+
+    pub fn crossing_closed(&self) -> bool {
+        self.secs_to_arrival() < CROSSING_CLOSE_SECS
+            || self.timer.elapsed_secs() < Self::pass_secs()
+    }
+}
+
+#[derive(Component)]
+pub struct Train;
+
+#[derive(Component)]
+pub struct CrossingLamp(usize);
+
+#[derive(Component)]
+pub struct CrossingBell;
+
+/// Temporary changes to the traffic applied by power-ups and the like.
+#[derive(Resource, Default)]
+pub struct TrafficModifiers {
+    pub speed: Scale,
+    pub spawn_rate: Scale,
+    /// How hard cars can brake, on wet roads say.
+    pub braking: Scale,
+    /// Road traffic drives right to left for the whole run.
+    pub mirrored: bool,
+}
+
+impl TrafficModifiers {
+    /// 1 when road traffic drives right, -1 when it drives left.
+    pub fn direction(&self) -> f32 {
+        if self.mirrored {
+            -1.
+        } else {
+            1.
+        }
+    }
 }
 
+/// All traffic randomness goes through this generator so a seed reproduces the same road.
+#[derive(Resource)]
+pub struct TrafficRng(pub StdRng);
+
+impl TrafficRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_enemy_system(
     mut commands: Commands,
     game_textures: Res<GameAssets>,
     mut spawn_timer: ResMut<EnemySpawnConfig>,
-    time: Res<Time>,
-    player_state: Res<PlayerState>,
+    mut traffic_rng: ResMut<TrafficRng>,
+    traffic: Res<TrafficModifiers>,
+    train_schedule: Res<TrainSchedule>,
+    level: Res<Level>,
+    vehicle_query: Query<(&Transform, &EnemyVelocity), With<Vehicle>>,
+    player_query: Query<(&Transform, &PlayerState, &PlayerModifiers), With<Player>>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    let online = lockstep.is_some();
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    // Fixed step, like the movement systems, so the same seed spawns the same traffic.
+    spawn_timer.timer.tick(Duration::from_secs_f32(
+        TIME_STEP * traffic.spawn_rate.value(),
+    ));
+
+    let anyone_alive = player_query
+        .iter()
+        .any(|(_, player_state, _)| player_state.alive);
+
+    if !spawn_timer.timer.finished() || !anyone_alive {
+        return;
+    }
+
+    let car_lanes = || level.car_lanes().map(|lane| lane.y);
+    let crossings: Vec<Crossing> = if online {
+        // Peers share the seed and the level, not where each chicken is, so an online race
+        // keeps the whole road crossable from either sidewalk instead.
+        [(PLAYER_START_Y, true), (-PLAYER_START_Y, false)]
+            .into_iter()
+            .map(|(y, heading_up)| {
+                Crossing::new(Vec2::new(0., y), car_lanes(), heading_up, BASE_SPEED)
+            })
+            .collect()
+    } else {
+        // Every chicken still in the run has to keep a way across.
+        player_query
+            .iter()
+            .filter(|(_, player_state, _)| player_state.alive)
+            .map(|(player_tf, player_state, modifiers)| {
+                Crossing::new(
+                    player_tf.translation.truncate(),
+                    car_lanes(),
+                    player_state.last_sidewalk != Sidewalk::Top,
+                    modifiers.speed.value() * BASE_SPEED,
+                )
+            })
+            .collect()
+    };
+
+    let lanes: Vec<_> = level
+        .car_lanes()
+        .filter(|lane| lane.spawn_rate > 0.)
+        .collect();
+
+    if lanes.is_empty() {
+        return;
+    }
+
+    let total_rate: f32 = lanes.iter().map(|lane| lane.spawn_rate).sum();
+
+    // Every roll is made whatever the outcome, so the seed stays in step with the traffic.
+    let rng = &mut traffic_rng.0;
+    let mut lane_roll = rng.gen_range(0. ..total_rate);
+    let color_roll: f32 = rng.gen();
+    let max_speed = rng.gen_range(CAR_SPEED_RANGE.0..CAR_SPEED_RANGE.1);
+
+    let preferred = lanes
+        .iter()
+        .position(|lane| {
+            lane_roll -= lane.spawn_rate;
+            lane_roll < 0.
+        })
+        .unwrap_or(0);
+
+    // The car model works as if traffic drove right; the fairness check doesn't care.
+    let direction_of = |lane: f32| level.direction(lane) * traffic.direction();
+    let mut cars = car_snapshots(vehicle_query.iter());
+    let forward_cars = driving_forward(&cars, direction_of);
+    let spawn_x = (-WIN_WIDTH / 2.) - (ENEMY_DIM.0 * ENEMY_SCALE);
+    let speed_scale = traffic.speed.value();
+    let brake = CAR_BRAKE * traffic.braking.value();
+
+    // Enter the preferred lane if it has room and stays crossable with this car in it,
+    // else another one. When no lane qualifies the car is held back until the next tick
+    // of the spawn timer.
+    let entry = (0..lanes.len())
+        .map(|offset| lanes[(preferred + offset) % lanes.len()])
+        .filter(|lane| lane.kind != LaneKind::Rail || !train_schedule.crossing_closed())
+        .find_map(|lane| {
+            let direction = direction_of(lane.y);
+            let lane_y = lane.y;
+
+            let speed = match car_ahead(&forward_cars, lane_y, spawn_x) {
+                Some(ahead) => {
+                    let gap = following_gap(spawn_x, ahead, 0.);
+                    if gap <= 0. {
+                        return None;
+                    }
+                    max_speed.min(stopping_speed(gap, ahead.speed, brake))
+                }
+                None => max_speed,
+            };
+
+            cars.push(CarState {
+                lane: lane_y,
+                x: spawn_x * direction,
+                speed: speed * direction,
+            });
+            let passable = crossings
+                .iter()
+                .all(|crossing| route_exists(&cars, crossing, speed_scale));
+            cars.pop();
+
+            passable.then_some((lane, speed))
+        });
+
+    let Some((lane, speed)) = entry else {
+        return;
+    };
+
+    let direction = direction_of(lane.y);
+    let red = color_roll < lane.red_share;
+    let position = Vec2::new(spawn_x * direction, lane.y);
+
+    spawn_car(
+        &mut commands,
+        &game_textures,
+        red,
+        position,
+        speed * direction,
+    )
+    .insert(Vehicle { max_speed });
+}
+
+/// Spawns a car centred on `position`, facing the way it drives.
+pub fn spawn_car<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    game_assets: &GameAssets,
+    red: bool,
+    position: Vec2,
+    velocity_x: f32,
+) -> EntityCommands<'w, 's, 'a> {
+    let direction = velocity_x.signum();
+    let texture = if red {
+        game_assets.enemy_red.clone()
+    } else {
+        game_assets.enemy_green.clone()
+    };
+
+    let mut car = commands.spawn(SpriteBundle {
+        texture,
+        sprite: Sprite {
+            flip_x: direction < 0.,
+            ..default()
+        },
+        transform: Transform {
+            translation: position.extend(2.),
+            scale: Vec3::splat(ENEMY_SCALE),
+            ..default()
+        },
+        ..default()
+    });
+
+    car.insert(EnemyVelocity { x: velocity_x })
+        .insert(ParticleEmitter::exhaust(Vec2::new(
+            -direction * ENEMY_DIM.0 * ENEMY_SCALE / 2.,
+            0.,
+        )))
+        .insert(Enemy)
+        .insert(Name::new("Enemy"));
+
+    car
+}
+
+pub fn spawn_train_system(
+    mut commands: Commands,
+    mut train_schedule: ResMut<TrainSchedule>,
+    traffic: Res<TrafficModifiers>,
+    level: Res<Level>,
+    lockstep: Option<Res<Lockstep>>,
 ) {
-    spawn_timer.timer.tick(time.delta());
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
 
-    if spawn_timer.timer.finished() && player_state.alive {
-        let mut rng = thread_rng();
-        let lane_index: usize = rng.gen_range(0..3);
-        let lanes = [FIRST_LANE, SECOND_LANE, THIRD_LANE];
+    // Trains keep to the timetable whatever the spawn rate, but slow down with the cars.
+    train_schedule
+        .timer
+        .tick(Duration::from_secs_f32(TIME_STEP * traffic.speed.value()));
+
+    if train_schedule.timer.just_finished() {
+        train_schedule.dispatched = false;
+    }
 
-        let car_color: u8 = rng.gen_range(0..2);
-        let car_sprite = if car_color == 0 {
-            game_textures.enemy_red.clone()
+    let secs_to_arrival = train_schedule.secs_to_arrival();
+    if train_schedule.dispatched || secs_to_arrival > TRAIN_WARNING_SECS {
+        return;
+    }
+    train_schedule.dispatched = true;
+
+    // Spawned far off screen so it reaches the crossing exactly on time. The autopilot
+    // sees it coming just like any other car.
+    let wagon_length = ENEMY_DIM.0 * ENEMY_SCALE;
+    let front_x = -WIN_WIDTH / 2. - wagon_length / 2. - secs_to_arrival * TRAIN_SPEED * BASE_SPEED;
+
+    for lane in level.lanes_of(LaneKind::Rail) {
+        let direction = lane.direction * traffic.direction();
+
+        spawn_train(
+            &mut commands,
+            Vec2::new(front_x * direction, lane.y),
+            TRAIN_SPEED * direction,
+        );
+    }
+}
+
+/// Spawns a locomotive at `front` with its wagons trailing behind it.
+pub fn spawn_train(commands: &mut Commands, front: Vec2, velocity_x: f32) {
+    let wagon_length = ENEMY_DIM.0 * ENEMY_SCALE;
+
+    for wagon in 0..TRAIN_WAGONS {
+        let color = if wagon == 0 {
+            COLOR_LOCOMOTIVE
         } else {
-            game_textures.enemy_green.clone()
+            COLOR_WAGON
         };
+        let x = front.x - velocity_x.signum() * wagon as f32 * wagon_length;
 
         commands
             .spawn(SpriteBundle {
-                texture: car_sprite,
+                sprite: Sprite {
+                    color: Color::rgb(color.0, color.1, color.2),
+                    custom_size: Some(Vec2::new(ENEMY_DIM.0, ENEMY_DIM.1)),
+                    ..default()
+                },
                 transform: Transform {
-                    translation: Vec3 {
-                        x: (-WIN_WIDTH / 2.) - (ENEMY_DIM.0 * ENEMY_SCALE),
-                        y: lanes[lane_index],
-                        z: 2.,
-                    },
-                    scale: Vec3 {
-                        x: 2.5,
-                        y: 2.5,
-                        z: 2.5,
-                    },
+                    translation: Vec3::new(x, front.y, 2.),
+                    scale: Vec3::splat(ENEMY_SCALE),
                     ..default()
                 },
                 ..default()
             })
-            .insert(EnemyVelocity { x: 1. })
+            .insert(EnemyVelocity { x: velocity_x })
             .insert(Enemy)
-            .insert(Name::new("Enemy"));
+            .insert(Train)
+            .insert(Name::new("Train"));
+    }
+}
+
+/// Tracks across the rail lane at `lane_y`, plus the signal post beside them on the side
+/// trains come from. `direction` is that of the traffic.
+pub fn spawn_level_crossing(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    lane_y: f32,
+    direction: f32,
+) {
+    let signal_y = lane_y + SIGNAL_OFFSET_Y;
+
+    for lamp in 0..2 {
+        commands
+            .spawn(signal_lamp(Vec3::new(
+                (SIGNAL_X + lamp as f32 * SIGNAL_LAMP_SIZE * 1.5) * direction,
+                signal_y,
+                1.6,
+            )))
+            .insert(CrossingLamp(lamp))
+            .insert(GameBackground)
+            .insert(Name::new("CrossingLamp"));
+    }
+
+    // There is no sound in the game yet, so the bell rings on screen.
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "DING DING",
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 20.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ),
+            transform: Transform::from_xyz((SIGNAL_X + 90.) * direction, signal_y, 1.6),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(CrossingBell)
+        .insert(GameBackground)
+        .insert(Name::new("CrossingBell"));
+}
+
+/// An unlit signal lamp at `position`.
+pub fn signal_lamp(position: Vec3) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: signal_color(false),
+            custom_size: Some(Vec2::splat(SIGNAL_LAMP_SIZE)),
+            ..default()
+        },
+        transform: Transform::from_translation(position),
+        ..default()
     }
 }
 
+pub fn signal_color(lit: bool) -> Color {
+    let (r, g, b) = if lit { COLOR_RED } else { COLOR_LAMP_OFF };
+    Color::rgb(r, g, b)
+}
+
+/// Which of a pair of blinking lamps is lit `secs` into the blinking.
+pub fn signal_phase(secs: f32) -> usize {
+    (secs * SIGNAL_BLINK_RATE) as usize % 2
+}
+
+/// Flashes the crossing lamps and rings the bell from the warning until the train is gone.
+pub fn crossing_signal_system(
+    train_schedule: Res<TrainSchedule>,
+    train_query: Query<(), With<Train>>,
+    mut lamp_query: Query<(&CrossingLamp, &mut Sprite)>,
+    mut bell_query: Query<&mut Visibility, With<CrossingBell>>,
+) {
+    let warning = train_schedule.secs_to_arrival() < TRAIN_WARNING_SECS || !train_query.is_empty();
+    let phase = signal_phase(train_schedule.timer.elapsed_secs());
+
+    for (lamp, mut sprite) in lamp_query.iter_mut() {
+        sprite.color = signal_color(warning && lamp.0 == phase);
+    }
+
+    for mut visibility in bell_query.iter_mut() {
+        visibility.is_visible = warning && phase == 0;
+    }
+}
+
+/// A car as the traffic model sees it: the cars behind it and the fair-spawn check.
+pub struct CarState {
+    pub lane: f32,
+    pub x: f32,
+    pub speed: f32,
+}
+
+pub fn car_snapshots<'a>(
+    cars: impl Iterator<Item = (&'a Transform, &'a EnemyVelocity)>,
+) -> Vec<CarState> {
+    cars.map(|(transform, velocity)| CarState {
+        lane: transform.translation.y,
+        x: transform.translation.x,
+        speed: velocity.x,
+    })
+    .collect()
+}
+
+/// The same cars as seen from traffic driving right, with positions and speeds mirrored
+/// in the lanes where it drives the other way.
+fn driving_forward(cars: &[CarState], direction_of: impl Fn(f32) -> f32) -> Vec<CarState> {
+    cars.iter()
+        .map(|car| {
+            let direction = direction_of(car.lane);
+            CarState {
+                lane: car.lane,
+                x: car.x * direction,
+                speed: car.speed * direction,
+            }
+        })
+        .collect()
+}
+
+/// The nearest car in `lane` in front of a car at `x`.
+fn car_ahead(cars: &[CarState], lane: f32, x: f32) -> Option<&CarState> {
+    cars.iter()
+        .filter(|car| (car.lane - lane).abs() < 1. && car.x > x)
+        .min_by(|a, b| a.x.total_cmp(&b.x))
+}
+
+/// Room left before a car at `x` driving at `speed` is closer to `ahead` than it wants to be.
+fn following_gap(x: f32, ahead: &CarState, speed: f32) -> f32 {
+    let length = ENEMY_DIM.0 * ENEMY_SCALE;
+    ahead.x - x - length - CAR_MIN_GAP - speed * CAR_HEADWAY_SECS * BASE_SPEED
+}
+
+/// Highest speed from which a car braking at `brake` can still stop within `gap` of a car
+/// moving at `speed_ahead` (zero for a stop line).
+fn stopping_speed(gap: f32, speed_ahead: f32, brake: f32) -> f32 {
+    (speed_ahead.powi(2) + 2. * brake * gap.max(0.) / BASE_SPEED).sqrt()
+}
+
+fn braking_distance(speed: f32, brake: f32) -> f32 {
+    speed.powi(2) * BASE_SPEED / (2. * brake)
+}
+
+/// Speed of a car for the next `dt`: it accelerates towards its cruising speed, but never
+/// faster than it could stop behind the car ahead or, in a crosswalk lane while the light
+/// is not green, before the stop line.
+fn car_speed(
+    position: Vec3,
+    speed: f32,
+    vehicle: &Vehicle,
+    cars: &[CarState],
+    crosswalk: Option<&TrafficLight>,
+    brake: f32,
+    dt: f32,
+) -> f32 {
+    let front = position.x + ENEMY_DIM.0 * ENEMY_SCALE / 2.;
+    let mut limit = vehicle.max_speed;
+
+    if let Some(ahead) = car_ahead(cars, position.y, position.x) {
+        let gap = following_gap(position.x, ahead, speed);
+        limit = limit.min(stopping_speed(gap, ahead.speed, brake));
+    }
+
+    // A car too close to stop when the light changes carries on through.
+    if crosswalk.is_some_and(|light| light.cars_stop()) {
+        let gap = STOP_LINE_X - front;
+        if gap >= 0. && braking_distance(speed, brake) <= gap + 1. {
+            limit = limit.min(stopping_speed(gap, 0., brake));
+        }
+    }
+
+    (speed + CAR_ACCEL * dt).min(limit)
+}
+
 pub fn move_enemy_system(
     mut commands: Commands,
-    mut enemies_query: Query<(Entity, &mut Transform, &EnemyVelocity), With<Enemy>>,
-    player_state: Res<PlayerState>,
+    mut enemies_query: Query<
+        (Entity, &mut Transform, &mut EnemyVelocity, Option<&Vehicle>),
+        With<Enemy>,
+    >,
+    traffic: Res<TrafficModifiers>,
+    light: Res<TrafficLight>,
+    level: Res<Level>,
+    player_query: Query<&PlayerState>,
+    lockstep: Option<Res<Lockstep>>,
 ) {
-    for (enemy_entity, mut transform, velocity) in enemies_query.iter_mut() {
-        transform.translation.x += velocity.x * TIME_STEP * BASE_SPEED;
-        if transform.translation.x > WIN_WIDTH + ENEMY_DIM.0 || !player_state.alive {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    let anyone_alive = player_query.iter().any(|player_state| player_state.alive);
+    let dt = TIME_STEP * traffic.speed.value();
+    let brake = CAR_BRAKE * traffic.braking.value();
+    let direction_of = |lane: f32| level.direction(lane) * traffic.direction();
+
+    // Trains run on rails and stop for nobody.
+    let cars = driving_forward(
+        &car_snapshots(
+            enemies_query
+                .iter()
+                .filter(|(.., vehicle)| vehicle.is_some())
+                .map(|(_, transform, velocity, _)| (transform, velocity)),
+        ),
+        direction_of,
+    );
+
+    for (enemy_entity, mut transform, mut velocity, vehicle) in enemies_query.iter_mut() {
+        let direction = direction_of(transform.translation.y);
+
+        if let Some(vehicle) = vehicle {
+            let position = Vec3::new(
+                transform.translation.x * direction,
+                transform.translation.y,
+                transform.translation.z,
+            );
+            let crosswalk = level
+                .lane_at(position.y)
+                .filter(|lane| lane.kind == LaneKind::Crosswalk)
+                .map(|_| &*light);
+            let speed = car_speed(
+                position,
+                velocity.x * direction,
+                vehicle,
+                &cars,
+                crosswalk,
+                brake,
+                dt,
+            );
+            velocity.x = speed * direction;
+        }
+
+        transform.translation.x += velocity.x * dt * BASE_SPEED;
+        if transform.translation.x * direction > WIN_WIDTH + ENEMY_DIM.0 || !anyone_alive {
             commands.entity(enemy_entity).despawn();
         }
     }
 }
 
+pub fn enemy_collides_with_player(enemy_tf: &Transform, player_tf: &Transform) -> bool {
+    let player_scale = player_tf.scale.xy();
+    let enemy_scale = enemy_tf.scale.xy();
+
+    collide(
+        player_tf.translation,
+        PLAYER_DIM * player_scale,
+        enemy_tf.translation,
+        ENEMY_DIM.0 * enemy_scale,
+    )
+    .is_some()
+}
+
+type HitPlayer<'a> = (
+    Entity,
+    &'a Player,
+    &'a mut Transform,
+    &'a mut PlayerState,
+    &'a mut Animator,
+    Option<&'a Invulnerable>,
+);
+
 pub fn enemy_hit_player_system(
     mut commands: Commands,
-    mut enemy_query: Query<&Transform, With<Enemy>>,
-    player_query: Query<(Entity, &Transform), With<Player>>,
-    mut player_state: ResMut<PlayerState>,
-    mut game_state: ResMut<State<GameState>>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Player>)>,
+    mut player_query: Query<HitPlayer>,
+    mut session: ResMut<Session>,
+    mut effects: ResMut<ActiveEffects>,
+    mut emit: EventWriter<EmitParticles>,
+    play_area: Res<PlayArea>,
 ) {
-    if let Ok((player_ent, player_tf)) = player_query.get_single() {
-        let player_scale = Vec2::from(player_tf.scale.xy());
-        for enemy_tf in enemy_query.iter_mut() {
-            let enemy_scale = Vec2::from(enemy_tf.scale.xy());
-
-            let collision = collide(
-                player_tf.translation,
-                PLAYER_DIM * player_scale,
-                enemy_tf.translation,
-                ENEMY_DIM.0 * enemy_scale,
-            );
+    for (player_ent, player, mut player_tf, mut player_state, mut animator, invulnerable) in
+        player_query.iter_mut()
+    {
+        let hit = enemy_query
+            .iter()
+            .any(|enemy_tf| enemy_collides_with_player(enemy_tf, &player_tf));
 
-            if let Some(_) = collision {
-                player_state.alive = false;
-                commands.entity(player_ent).despawn();
+        if !hit || invulnerable.is_some() {
+            continue;
+        }
 
-                game_state.set(GameState::GameOver).unwrap();
-            }
+        emit.send(EmitParticles {
+            burst: Burst::Feathers,
+            position: player_tf.translation.truncate(),
+        });
+
+        if effects.absorb_hit(player.id) {
+            commands.entity(player_ent).insert(Invulnerable::default());
+            animator.play(Clip::Hit);
+            continue;
+        }
+
+        if lose_life(
+            player,
+            &mut player_tf,
+            &mut player_state,
+            &mut animator,
+            &mut session,
+            play_area.respawn_y,
+        ) {
+            return;
         }
     }
 }
@@ -0,0 +1,408 @@
+use crate::{
+    enemy::{
+        car_snapshots, CarState, EnemyVelocity, TrafficModifiers, Vehicle, ENEMY_DIM, ENEMY_SCALE,
+    },
+    in_game::GameBackground,
+    level::Level,
+    player::{Player, PlayerModifiers, ARENA_LEFT, ARENA_RIGHT},
+    GameState, BASE_SPEED, PLAYER_DIM,
+};
+use bevy::prelude::*;
+
+/// How far ahead the spawner looks when deciding whether the road can still be crossed.
+pub const HORIZON_SECS: f32 = 6.;
+const COLUMN_WIDTH: f32 = 40.;
+const SAFETY_MARGIN: f32 = 8.;
+
+const OVERLAY_ALPHA: f32 = 0.3;
+const OVERLAY_HEIGHT: f32 = ENEMY_DIM.1 * ENEMY_SCALE;
+
+/// Half the width of the strip around a column that must be free of cars for the
+/// chicken to pass through it.
+const REACH: f32 = ENEMY_DIM.0 * ENEMY_SCALE / 2. + PLAYER_DIM + SAFETY_MARGIN;
+
+/// Distance the chicken walks from touching a car lane's traffic to being clear of it.
+const LANE_DEPTH: f32 = ENEMY_DIM.1 * ENEMY_SCALE + PLAYER_DIM * 2.;
+
+/// Time a chicken walking at `speed` pixels per second spends inside a car lane's hitbox
+/// when walking straight across it.
+fn crossing_secs(speed: f32) -> f32 {
+    LANE_DEPTH / speed
+}
+
+fn columns() -> impl Iterator<Item = f32> {
+    let count = ((ARENA_RIGHT - ARENA_LEFT) / COLUMN_WIDTH) as usize;
+    (0..=count).map(|column| ARENA_LEFT + column as f32 * COLUMN_WIDTH)
+}
+
+/// Intervals within the horizon during which no car in `lane` covers the column at `x`,
+/// assuming every car keeps its current speed. Speeds are scaled by `speed_scale`.
+pub fn safe_windows(cars: &[CarState], lane: f32, x: f32, speed_scale: f32) -> Vec<(f32, f32)> {
+    let mut blocked: Vec<(f32, f32)> = cars
+        .iter()
+        .filter(|car| (car.lane - lane).abs() < 1.)
+        .filter_map(|car| {
+            let offset = car.x - x;
+            let speed = car.speed * speed_scale * BASE_SPEED;
+
+            let (start, end) = if speed == 0. {
+                if offset.abs() >= REACH {
+                    return None;
+                }
+                (0., HORIZON_SECS)
+            } else {
+                let enter = (-REACH - offset) / speed;
+                let leave = (REACH - offset) / speed;
+                (enter.min(leave).max(0.), enter.max(leave).min(HORIZON_SECS))
+            };
+
+            (start < end).then_some((start, end))
+        })
+        .collect();
+    blocked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut windows = Vec::new();
+    let mut free_from = 0.;
+    for (start, end) in blocked {
+        if start > free_from {
+            windows.push((free_from, start));
+        }
+        free_from = f32::max(free_from, end);
+    }
+    if free_from < HORIZON_SECS {
+        windows.push((free_from, HORIZON_SECS));
+    }
+
+    windows
+}
+
+/// When a chicken reaching a column at `arrival` and needing `cross` seconds to walk
+/// through can be out the other side, given the safe windows of that column.
+fn earliest_exit(windows: &[(f32, f32)], arrival: f32, cross: f32) -> Option<f32> {
+    windows.iter().find_map(|(start, end)| {
+        let enter = start.max(arrival);
+        (enter + cross <= *end).then_some(enter + cross)
+    })
+}
+
+/// A chicken about to cross the road.
+pub struct Crossing {
+    pub start: Vec2,
+    /// Car lanes still ahead of it, in the order it meets them.
+    pub lanes: Vec<f32>,
+    /// Walking speed, in pixels per second.
+    pub speed: f32,
+}
+
+impl Crossing {
+    /// The car lanes of `lanes` the chicken at `start` has not got past yet, heading up the
+    /// screen or down it.
+    pub fn new(
+        start: Vec2,
+        lanes: impl Iterator<Item = f32>,
+        heading_up: bool,
+        speed: f32,
+    ) -> Self {
+        let ahead = if heading_up { 1. } else { -1. };
+        let mut lanes: Vec<f32> = lanes
+            .filter(|lane| (lane - start.y) * ahead > -LANE_DEPTH / 2.)
+            .collect();
+        lanes.sort_by(|a, b| (a * ahead).total_cmp(&(b * ahead)));
+
+        Self {
+            start,
+            lanes,
+            speed,
+        }
+    }
+}
+
+/// Whether the chicken can get through every car lane ahead of it within the horizon,
+/// assuming every car keeps its current speed. It may walk sideways and wait between lanes,
+/// moving both ways at once as the controls allow.
+pub fn route_exists(cars: &[CarState], crossing: &Crossing, speed_scale: f32) -> bool {
+    let xs: Vec<f32> = columns().collect();
+    let cross = crossing_secs(crossing.speed);
+    let walk = |dx: f32, dy: f32| dx.abs().max(dy.max(0.)) / crossing.speed;
+
+    let mut y = crossing.start.y;
+    // Earliest time the chicken can stand at each column just before the next lane, and
+    // then just past it.
+    let mut ready = vec![0.];
+    let mut from_xs = vec![crossing.start.x];
+
+    for (index, &lane) in crossing.lanes.iter().enumerate() {
+        let gap = if index == 0 {
+            (lane - y).abs() - LANE_DEPTH / 2.
+        } else {
+            (lane - y).abs() - LANE_DEPTH
+        };
+
+        let arrivals: Vec<f32> = xs
+            .iter()
+            .map(|x| {
+                from_xs
+                    .iter()
+                    .zip(ready.iter())
+                    .map(|(from, at)| at + walk(x - from, gap))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .collect();
+
+        ready = xs
+            .iter()
+            .zip(arrivals)
+            .map(|(x, arrival)| {
+                earliest_exit(&safe_windows(cars, lane, *x, speed_scale), arrival, cross)
+                    .unwrap_or(f32::INFINITY)
+            })
+            .collect();
+
+        if ready.iter().all(|at| at.is_infinite()) {
+            return false;
+        }
+
+        from_xs = xs.clone();
+        y = lane;
+    }
+
+    true
+}
+
+/// Shows where and when the spawner thinks the road can be crossed. Toggled with F3.
+#[derive(Resource, Default)]
+pub struct FairnessOverlay {
+    pub enabled: bool,
+}
+
+#[derive(Component)]
+struct OverlayCell {
+    lane: f32,
+    x: f32,
+}
+
+pub struct FairnessPlugin;
+
+impl Plugin for FairnessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FairnessOverlay::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(spawn_overlay_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(toggle_overlay_system)
+                    .with_system(overlay_system),
+            );
+    }
+}
+
+fn spawn_overlay_system(mut commands: Commands, level: Res<Level>) {
+    for lane in level.car_lanes().map(|lane| lane.y) {
+        for x in columns() {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(COLUMN_WIDTH - 2., OVERLAY_HEIGHT)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x, lane, 1.8),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(OverlayCell { lane, x })
+                // Torn down with the rest of the level.
+                .insert(GameBackground)
+                .insert(Name::new("FairnessOverlay"));
+        }
+    }
+}
+
+fn toggle_overlay_system(kb: Res<Input<KeyCode>>, mut overlay: ResMut<FairnessOverlay>) {
+    if kb.just_pressed(KeyCode::F3) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+/// Green: crossable now. Yellow: a gap opens within the horizon. Red: no gap in sight.
+fn overlay_system(
+    overlay: Res<FairnessOverlay>,
+    traffic: Res<TrafficModifiers>,
+    vehicle_query: Query<(&Transform, &EnemyVelocity), With<Vehicle>>,
+    player_query: Query<(&Player, &PlayerModifiers)>,
+    mut cell_query: Query<(&OverlayCell, &mut Sprite, &mut Visibility)>,
+) {
+    if !overlay.enabled {
+        for (_, _, mut visibility) in cell_query.iter_mut() {
+            visibility.is_visible = false;
+        }
+        return;
+    }
+
+    let cars = car_snapshots(vehicle_query.iter());
+    let speed_scale = traffic.speed.value();
+    let cross = player_query
+        .iter()
+        .find(|(player, _)| player.id == 0)
+        .map_or(crossing_secs(BASE_SPEED), |(_, modifiers)| {
+            crossing_secs(modifiers.speed.value() * BASE_SPEED)
+        });
+
+    for (cell, mut sprite, mut visibility) in cell_query.iter_mut() {
+        let windows: Vec<(f32, f32)> = safe_windows(&cars, cell.lane, cell.x, speed_scale)
+            .into_iter()
+            .filter(|(start, end)| end - start >= cross)
+            .collect();
+
+        let (r, g, b) = match windows.first() {
+            Some((start, _)) if *start == 0. => (0.2, 0.9, 0.3),
+            Some(_) => (1., 0.85, 0.1),
+            None => (0.95, 0.2, 0.2),
+        };
+
+        sprite.color = Color::rgba(r, g, b, OVERLAY_ALPHA);
+        visibility.is_visible = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car(lane: f32, x: f32, speed: f32) -> CarState {
+        CarState { lane, x, speed }
+    }
+
+    /// Parked cars filling the whole lane at `lane`, except around `gap_x` if given.
+    fn wall(lane: f32, gap_x: Option<f32>) -> Vec<CarState> {
+        columns()
+            .filter(|x| gap_x.is_none_or(|gap_x| (x - gap_x).abs() >= REACH))
+            .map(|x| car(lane, x, 0.))
+            .collect()
+    }
+
+    #[test]
+    fn empty_lane_is_one_window() {
+        assert_eq!(safe_windows(&[], 0., 0., 1.), vec![(0., HORIZON_SECS)]);
+    }
+
+    #[test]
+    fn parked_car_blocks_its_column_only() {
+        let cars = [car(0., 0., 0.)];
+
+        assert!(safe_windows(&cars, 0., 0., 1.).is_empty());
+        assert_eq!(safe_windows(&cars, 0., 200., 1.), vec![(0., HORIZON_SECS)]);
+        assert_eq!(safe_windows(&cars, 100., 0., 1.), vec![(0., HORIZON_SECS)]);
+    }
+
+    #[test]
+    fn passing_car_splits_the_window() {
+        let cars = [car(0., -500., 1.)];
+        let enter = (500. - REACH) / BASE_SPEED;
+        let leave = (500. + REACH) / BASE_SPEED;
+
+        let windows = safe_windows(&cars, 0., 0., 1.);
+        assert_eq!(windows.len(), 2);
+        assert!((windows[0].1 - enter).abs() < 1e-4);
+        assert!((windows[1].0 - leave).abs() < 1e-4);
+
+        // Slowed down traffic takes twice as long to get there.
+        let slowed = safe_windows(&cars, 0., 0., 0.5);
+        assert!((slowed[0].1 - enter * 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn exit_waits_for_a_window_long_enough() {
+        let windows = [(0., 1.), (2., HORIZON_SECS)];
+
+        assert_eq!(earliest_exit(&windows, 0., 0.5), Some(0.5));
+        assert_eq!(earliest_exit(&windows, 0.8, 0.5), Some(2.5));
+        assert_eq!(earliest_exit(&windows, 0., 1.5), Some(3.5));
+        assert_eq!(earliest_exit(&windows, HORIZON_SECS, 0.5), None);
+    }
+
+    #[test]
+    fn only_lanes_ahead_are_crossed() {
+        let lanes = [-200., 0., 200.];
+
+        let up = Crossing::new(Vec2::new(0., -100.), lanes.into_iter(), true, BASE_SPEED);
+        assert_eq!(up.lanes, vec![0., 200.]);
+
+        let down = Crossing::new(Vec2::new(0., 100.), lanes.into_iter(), false, BASE_SPEED);
+        assert_eq!(down.lanes, vec![0., -200.]);
+    }
+
+    #[test]
+    fn route_across_an_empty_road() {
+        let crossing = Crossing::new(
+            Vec2::new(0., -300.),
+            [-100., 100.].into_iter(),
+            true,
+            BASE_SPEED,
+        );
+
+        assert!(route_exists(&[], &crossing, 1.));
+    }
+
+    #[test]
+    fn a_wall_of_cars_has_no_route() {
+        let crossing = Crossing::new(
+            Vec2::new(0., -300.),
+            [-100., 100.].into_iter(),
+            true,
+            BASE_SPEED,
+        );
+
+        assert!(!route_exists(&wall(100., None), &crossing, 1.));
+    }
+
+    #[test]
+    fn route_walks_sideways_to_a_gap() {
+        let crossing = Crossing::new(
+            Vec2::new(0., -300.),
+            [-100., 100.].into_iter(),
+            true,
+            BASE_SPEED,
+        );
+        let last_column = columns().last().unwrap();
+        let mut cars = wall(-100., Some(ARENA_LEFT));
+        cars.extend(wall(100., Some(last_column)));
+
+        assert!(route_exists(&cars, &crossing, 1.));
+    }
+
+    #[test]
+    fn gap_out_of_reach_in_time_is_no_route() {
+        // A slow car closes the only gap before a chicken across the road can get there,
+        // but not before one standing next to it does.
+        let mut cars = wall(-100., Some(ARENA_LEFT));
+        cars.push(car(-100., ARENA_LEFT - 100., 0.05));
+        let lanes = [-100.];
+
+        let far = Crossing::new(
+            Vec2::new(ARENA_RIGHT, -300.),
+            lanes.into_iter(),
+            true,
+            BASE_SPEED,
+        );
+        let near = Crossing::new(
+            Vec2::new(ARENA_LEFT, -300.),
+            lanes.into_iter(),
+            true,
+            BASE_SPEED,
+        );
+
+        assert!(!route_exists(&cars, &far, 1.));
+        assert!(route_exists(&cars, &near, 1.));
+        assert!(route_exists(&cars[..cars.len() - 1], &far, 1.));
+
+        // Slowed down, even the chicken next to the gap misses it.
+        let slow = Crossing::new(
+            Vec2::new(ARENA_LEFT, -300.),
+            lanes.into_iter(),
+            true,
+            BASE_SPEED * 0.5,
+        );
+        assert!(!route_exists(&cars, &slow, 1.));
+    }
+}
@@ -1,6 +1,10 @@
 use crate::{
-    menu::{button_control_system, despawn_ui_system, Ancestor},
-    player::PlayerState,
+    daily::{date_label, DailyChallenge, DailyResults},
+    menu::{button_control_system, despawn_ui_system, Ancestor, StartButton},
+    modes::{crossings, format_record, run_result, SPRINT_CROSSINGS, TIME_ATTACK_SECS},
+    mutators::mutators_summary,
+    player::{GameMode, PlayerMode, Session},
+    scores::HighScores,
     GameAssets, GameState, COLOR_RED, COLOR_YELLOW, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
 };
 use bevy::prelude::*;
@@ -18,11 +22,54 @@ impl Plugin for GameOverPlugin {
     }
 }
 
-fn setup_game_over_ui_system(
+pub fn setup_game_over_ui_system(
     mut commands: Commands,
     game_assets: Res<GameAssets>,
-    player_state: Res<PlayerState>,
+    session: Res<Session>,
+    high_scores: Res<HighScores>,
+    daily: Res<DailyChallenge>,
+    daily_results: Res<DailyResults>,
 ) {
+    let result = run_result(&session);
+    let summary = match (session.game_mode, session.mode, session.winner) {
+        (GameMode::Endless, ..) => format!("You crossed {} lanes", session.score),
+        (GameMode::TimeAttack, ..) => format!(
+            "You crossed {} times in {} seconds",
+            crossings(&session),
+            TIME_ATTACK_SECS
+        ),
+        (GameMode::Sprint, ..) => match result {
+            Some(time) => format!("{} crossings in {:.1} seconds", SPRINT_CROSSINGS, time),
+            None => format!(
+                "Run over after {} of {} crossings",
+                crossings(&session),
+                SPRINT_CROSSINGS
+            ),
+        },
+        (GameMode::Survival, ..) => format!("You survived {:.1} seconds", session.time),
+        (_, PlayerMode::Versus, Some(winner)) => format!("Player {} wins the race", winner + 1),
+        _ => format!("You reached the level {}", session.level),
+    };
+
+    let details = if session.game_mode == GameMode::Classic {
+        format!("Corn collected: {}", session.corn)
+    } else if session.game_mode == GameMode::Daily {
+        let date = date_label(daily.day);
+        match daily_results.days.get(&daily.day) {
+            Some(scored) if !daily.scored => {
+                format!("Practice run. Daily {}: level {}", date, scored.level)
+            }
+            _ => format!("Daily {} saved, corn collected: {}", date, session.corn),
+        }
+    } else if session.new_record {
+        "New record!".to_string()
+    } else {
+        match high_scores.record(session.game_mode, session.mutators) {
+            Some(best) => format!("Best: {}", format_record(session.game_mode, best)),
+            None => String::new(),
+        }
+    };
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -49,7 +96,17 @@ fn setup_game_over_ui_system(
         })
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
-                format!("You reached the level {}", player_state.level),
+                summary,
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 25.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ));
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                details,
                 TextStyle {
                     font: game_assets.font.clone(),
                     font_size: 25.,
@@ -57,6 +114,19 @@ fn setup_game_over_ui_system(
                 },
             ));
         })
+        .with_children(|parent| {
+            let Some(mutators) = mutators_summary(session.mutators) else {
+                return;
+            };
+            parent.spawn(TextBundle::from_section(
+                mutators,
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 20.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ));
+        })
         .with_children(|parent| {
             parent
                 .spawn(ButtonBundle {
@@ -69,6 +139,11 @@ fn setup_game_over_ui_system(
                     background_color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2).into(),
                     ..default()
                 })
+                .insert(StartButton {
+                    players: session.mode,
+                    game: session.game_mode,
+                    ghost_race: session.ghost_race,
+                })
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "PLAY AGAIN",
@@ -0,0 +1,172 @@
+use crate::{
+    animation::idle_sprite_index,
+    net::Lockstep,
+    player::{Direction, GameMode, Player, PlayerMode, Session},
+    scores::{GhostFrame, HighScores, ScoreEntry},
+    GameAssets, GameState,
+};
+use bevy::prelude::*;
+
+const GHOST_ALPHA: f32 = 0.35;
+
+/// Positions of the local chicken for every simulated tick of the current run.
+#[derive(Resource, Default)]
+pub struct RunRecorder {
+    pub frames: Vec<GhostFrame>,
+}
+
+#[derive(Component)]
+pub struct Ghost {
+    frames: Vec<GhostFrame>,
+    frame: usize,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunRecorder::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(start_recording_system)
+                    .with_system(spawn_ghost_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(record_run_system)
+                    .with_system(play_ghost_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(despawn_ghost_system))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(save_best_run_system),
+            );
+    }
+}
+
+fn start_recording_system(mut recorder: ResMut<RunRecorder>) {
+    recorder.frames.clear();
+}
+
+fn spawn_ghost_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    high_scores: Res<HighScores>,
+    session: Res<Session>,
+) {
+    if session.mode != PlayerMode::Single
+        || session.game_mode != GameMode::Classic
+        || !session.ghost_race
+        || session.test_play
+    {
+        return;
+    }
+
+    let Some(best) = &high_scores.best else {
+        return;
+    };
+
+    // The ghost replays inputs against the traffic of its own run, and only that.
+    if best.seed != session.seed || best.mutators != session.mutators {
+        return;
+    }
+
+    let Some(first) = best.ghost.first() else {
+        return;
+    };
+
+    commands
+        .spawn(SpriteSheetBundle {
+            texture_atlas: game_assets.player.clone(),
+            sprite: TextureAtlasSprite {
+                index: idle_sprite_index(first.direction),
+                color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                ..default()
+            },
+            transform: Transform {
+                translation: first.position.extend(0.9),
+                scale: Vec3::splat(2.),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Ghost {
+            frames: best.ghost.clone(),
+            frame: 0,
+        })
+        .insert(Name::new("Ghost"));
+}
+
+fn record_run_system(
+    mut recorder: ResMut<RunRecorder>,
+    session: Res<Session>,
+    player_query: Query<(&Player, &Transform, &Direction)>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if session.mode != PlayerMode::Single || lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    if let Some((_, player_tf, direction)) = player_query.iter().find(|(player, ..)| player.id == 0)
+    {
+        recorder.frames.push(GhostFrame {
+            position: player_tf.translation.truncate(),
+            direction: *direction,
+        });
+    }
+}
+
+fn play_ghost_system(
+    mut ghost_query: Query<(
+        &mut Ghost,
+        &mut Transform,
+        &mut TextureAtlasSprite,
+        &mut Visibility,
+    )>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    for (mut ghost, mut transform, mut sprite, mut visibility) in ghost_query.iter_mut() {
+        match ghost.frames.get(ghost.frame).copied() {
+            Some(frame) => {
+                transform.translation.x = frame.position.x;
+                transform.translation.y = frame.position.y;
+                sprite.index = idle_sprite_index(frame.direction);
+                ghost.frame += 1;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
+
+fn despawn_ghost_system(mut commands: Commands, ghost_query: Query<Entity, With<Ghost>>) {
+    for ent in ghost_query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
+
+fn save_best_run_system(
+    mut recorder: ResMut<RunRecorder>,
+    mut high_scores: ResMut<HighScores>,
+    session: Res<Session>,
+) {
+    if session.mode != PlayerMode::Single
+        || session.game_mode != GameMode::Classic
+        || session.test_play
+    {
+        return;
+    }
+
+    let entry = ScoreEntry {
+        level: session.level,
+        seed: session.seed,
+        mutators: session.mutators,
+        ghost: std::mem::take(&mut recorder.frames),
+    };
+
+    if high_scores.submit(entry) {
+        high_scores.save();
+    }
+}
@@ -0,0 +1,430 @@
+use crate::{
+    endless::endless_setup_system,
+    in_game::in_game_setup_system,
+    modes::{clock_label, format_result},
+    player::{GameMode, Player, PlayerMode, PlayerState, Session, VERSUS_TARGET_CROSSINGS},
+    powerup::{ActiveEffects, PowerUpKind, POWER_UP_KINDS},
+    scores::HighScores,
+    GameAssets, GameState, COLOR_RED, COLOR_YELLOW,
+};
+use bevy::prelude::*;
+
+const MARGIN: f32 = 10.;
+const CAPTION_SIZE: f32 = 16.;
+const VALUE_SIZE: f32 = 30.;
+/// Values grow to this scale when they change, then settle back.
+const POP_SCALE: f32 = 1.4;
+const POP_SECS: f32 = 0.3;
+/// Share of the gap to the real score the shown score closes per second.
+const ROLL_RATE: f32 = 8.;
+/// Combos are only shown from this many crossings in a row.
+const MIN_COMBO: u32 = 2;
+const ICON_SIZE: f32 = 70.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    /// Running power-ups.
+    BottomRight,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HudField {
+    Score,
+    Best,
+    Level,
+    Lives,
+    Corn,
+    Clock,
+    Combo,
+}
+
+impl HudField {
+    fn corner(&self) -> Corner {
+        match self {
+            HudField::Score | HudField::Best => Corner::TopLeft,
+            HudField::Level | HudField::Lives | HudField::Corn => Corner::TopRight,
+            HudField::Clock | HudField::Combo => Corner::BottomLeft,
+        }
+    }
+
+    fn caption(&self, mode: PlayerMode) -> &'static str {
+        match self {
+            HudField::Score => "SCORE",
+            HudField::Best => "BEST",
+            HudField::Level if mode == PlayerMode::Versus => "CROSSINGS",
+            HudField::Level => "LEVEL",
+            HudField::Lives => "LIVES",
+            HudField::Corn => "CORN",
+            HudField::Clock => "TIME",
+            HudField::Combo => "COMBO",
+        }
+    }
+
+    /// The number whose changes make the value pop, for the values that pop at all.
+    fn watched(&self, session: &Session, crossings: u32) -> Option<u32> {
+        match self {
+            HudField::Score => Some(session.score),
+            HudField::Level => Some(crossings),
+            HudField::Lives => Some(u32::from(session.lives)),
+            HudField::Corn => Some(session.corn),
+            HudField::Combo => Some(session.combo),
+            HudField::Best | HudField::Clock => None,
+        }
+    }
+
+    fn shown(&self, session: &Session) -> bool {
+        match self {
+            HudField::Lives => session.mode != PlayerMode::Versus,
+            HudField::Combo => session.combo >= MIN_COMBO,
+            // Endless counts lanes as its score, with no levels, clock or corn.
+            HudField::Level | HudField::Corn | HudField::Clock => {
+                session.game_mode != GameMode::Endless
+            }
+            HudField::Score | HudField::Best => true,
+        }
+    }
+}
+
+const HUD_FIELDS: [HudField; 7] = [
+    HudField::Score,
+    HudField::Best,
+    HudField::Level,
+    HudField::Lives,
+    HudField::Corn,
+    HudField::Clock,
+    HudField::Combo,
+];
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component)]
+struct PowerUpIcon(PowerUpKind);
+
+#[derive(Component)]
+struct PowerUpCountdown(PowerUpKind);
+
+#[derive(Component)]
+struct HudValue {
+    field: HudField,
+    watched: Option<u32>,
+    pop: Timer,
+    /// Colour the value flashes when it changes.
+    flash: Color,
+    /// Score shown while it rolls up to the real one.
+    rolled: f32,
+}
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::InGame)
+                .with_system(setup_hud_system.after(in_game_setup_system)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Endless)
+                .with_system(setup_hud_system.after(endless_setup_system)),
+        );
+
+        for state in [GameState::InGame, GameState::Endless] {
+            app.add_system_set(
+                SystemSet::on_update(state.clone())
+                    .with_system(hud_system)
+                    .with_system(power_up_icons_system),
+            )
+            .add_system_set(SystemSet::on_exit(state).with_system(despawn_hud_system));
+        }
+
+        // The death sequence pauses the run, but the life it cost shows straight away.
+        app.add_system_set(SystemSet::on_update(GameState::Dying).with_system(hud_system));
+    }
+}
+
+fn setup_hud_system(mut commands: Commands, game_assets: Res<GameAssets>, session: Res<Session>) {
+    let caption_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: CAPTION_SIZE,
+        color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+    };
+    let value_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: VALUE_SIZE,
+        color: Color::WHITE,
+    };
+
+    for corner in [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ] {
+        let (position, align_items) = match corner {
+            Corner::TopLeft => (
+                UiRect {
+                    left: Val::Px(MARGIN),
+                    top: Val::Px(MARGIN),
+                    ..default()
+                },
+                AlignItems::FlexStart,
+            ),
+            Corner::TopRight => (
+                UiRect {
+                    right: Val::Px(MARGIN),
+                    top: Val::Px(MARGIN),
+                    ..default()
+                },
+                AlignItems::FlexEnd,
+            ),
+            Corner::BottomLeft => (
+                UiRect {
+                    left: Val::Px(MARGIN),
+                    bottom: Val::Px(MARGIN),
+                    ..default()
+                },
+                AlignItems::FlexStart,
+            ),
+            Corner::BottomRight => (
+                UiRect {
+                    right: Val::Px(MARGIN),
+                    bottom: Val::Px(MARGIN),
+                    ..default()
+                },
+                AlignItems::FlexEnd,
+            ),
+        };
+
+        if corner == Corner::BottomRight {
+            spawn_power_up_icons(&mut commands, &game_assets, position);
+            continue;
+        }
+
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    flex_direction: FlexDirection::Column,
+                    align_items,
+                    padding: UiRect::all(Val::Px(6.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.35).into(),
+                ..default()
+            })
+            .insert(Hud)
+            .insert(Name::new("Hud"))
+            .with_children(|parent| {
+                for field in HUD_FIELDS.iter().filter(|field| field.corner() == corner) {
+                    // Settled from the start, so nothing pops before it changes.
+                    let mut pop = Timer::from_seconds(POP_SECS, TimerMode::Once);
+                    pop.set_elapsed(pop.duration());
+
+                    parent
+                        .spawn(TextBundle::from_sections([
+                            TextSection::new(
+                                format!("{}\n", field.caption(session.mode)),
+                                caption_style.clone(),
+                            ),
+                            TextSection::new("", value_style.clone()),
+                        ]))
+                        .insert(HudValue {
+                            field: *field,
+                            watched: field.watched(&session, 0),
+                            pop,
+                            flash: Color::WHITE,
+                            rolled: session.score as f32,
+                        });
+                }
+            });
+    }
+}
+
+fn hud_system(
+    session: Res<Session>,
+    high_scores: Res<HighScores>,
+    player_query: Query<(&Player, &PlayerState)>,
+    mut value_query: Query<(&mut HudValue, &mut Text, &mut Style, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let mut players: Vec<_> = player_query.iter().collect();
+    players.sort_by_key(|(player, _)| player.id);
+    let crossings = players
+        .iter()
+        .map(|(_, player_state)| u32::from(player_state.crossings()))
+        .sum();
+
+    for (mut value, mut text, mut style, mut transform) in value_query.iter_mut() {
+        let field = value.field;
+
+        let display = if field.shown(&session) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+
+        if field == HudField::Score {
+            let gap = session.score as f32 - value.rolled;
+            value.rolled += gap * (ROLL_RATE * time.delta_seconds()).min(1.);
+            if gap.abs() < 1. {
+                value.rolled = session.score as f32;
+            }
+        }
+
+        text.sections[1].value = match field {
+            HudField::Score => format!("{}", value.rolled.round() as u32),
+            HudField::Best => best_label(&session, &high_scores),
+            HudField::Level if session.mode == PlayerMode::Versus => players
+                .iter()
+                .map(|(player, player_state)| {
+                    format!(
+                        "P{} {}/{}",
+                        player.id + 1,
+                        player_state.crossings(),
+                        VERSUS_TARGET_CROSSINGS
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("  "),
+            HudField::Level => format!("{}", session.level),
+            HudField::Lives => format!("{}", session.lives),
+            HudField::Corn => format!("{}", session.corn),
+            HudField::Clock => clock_label(&session),
+            HudField::Combo => format!("x{}", session.combo),
+        };
+
+        let watched = field.watched(&session, crossings);
+        if watched != value.watched {
+            // Losing a life flashes red and everything else going up flashes yellow. Other
+            // drops, like a combo ending, just show the new value.
+            let flash = match (field, watched < value.watched) {
+                (HudField::Lives, true) => Some(COLOR_RED),
+                (_, false) => Some(COLOR_YELLOW),
+                (_, true) => None,
+            };
+            if let Some((r, g, b)) = flash {
+                value.flash = Color::rgb(r, g, b);
+                value.pop.reset();
+            }
+            value.watched = watched;
+        }
+
+        value.pop.tick(time.delta());
+        let left = value.pop.percent_left();
+        transform.scale = Vec3::splat(1. + (POP_SCALE - 1.) * left);
+
+        let flash = Vec4::from(value.flash.as_rgba_f32());
+        text.sections[1].style.color = Color::from(Vec4::ONE.lerp(flash, left));
+    }
+}
+
+/// A row of icons, one per kind of power-up, each only shown while that power-up runs.
+fn spawn_power_up_icons(commands: &mut Commands, game_assets: &GameAssets, position: UiRect) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position,
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Hud)
+        .insert(Name::new("Hud"))
+        .with_children(|parent| {
+            for kind in POWER_UP_KINDS {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                            margin: UiRect::all(Val::Px(5.)),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            display: Display::None,
+                            ..default()
+                        },
+                        background_color: kind.color().into(),
+                        ..default()
+                    })
+                    .insert(PowerUpIcon(kind))
+                    .with_children(|parent| {
+                        parent
+                            .spawn(TextBundle::from_sections([
+                                TextSection::new(
+                                    format!("{}\n", kind.label()),
+                                    TextStyle {
+                                        font: game_assets.font.clone(),
+                                        font_size: CAPTION_SIZE,
+                                        color: Color::WHITE,
+                                    },
+                                ),
+                                TextSection::new(
+                                    "",
+                                    TextStyle {
+                                        font: game_assets.font.clone(),
+                                        font_size: 24.,
+                                        color: Color::WHITE,
+                                    },
+                                ),
+                            ]))
+                            .insert(PowerUpCountdown(kind));
+                    });
+            }
+        });
+}
+
+fn power_up_icons_system(
+    effects: Res<ActiveEffects>,
+    mut icon_query: Query<(&PowerUpIcon, &mut Style)>,
+    mut countdown_query: Query<(&PowerUpCountdown, &mut Text)>,
+) {
+    for (icon, mut style) in icon_query.iter_mut() {
+        let display = if effects.remaining_secs(icon.0).is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    for (countdown, mut text) in countdown_query.iter_mut() {
+        if let Some(remaining) = effects.remaining_secs(countdown.0) {
+            text.sections[1].value = format!("{:.1}", remaining);
+        }
+    }
+}
+
+/// Best result of the mode being played, as the leaderboards show it.
+fn best_label(session: &Session, high_scores: &HighScores) -> String {
+    let best = match session.game_mode {
+        GameMode::Classic => high_scores
+            .best
+            .as_ref()
+            .map(|best| format_result(GameMode::Classic, f32::from(best.level))),
+        // One attempt a day, so there is nothing to beat.
+        GameMode::Daily => None,
+        mode => high_scores
+            .record(mode, session.mutators)
+            .map(|record| format_result(mode, record.value)),
+    };
+
+    best.unwrap_or_else(|| "-".to_string())
+}
+
+fn despawn_hud_system(mut commands: Commands, hud_query: Query<Entity, With<Hud>>) {
+    for ent in hud_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
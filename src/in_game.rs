@@ -1,15 +1,26 @@
-use std::time::Duration;
-
 use crate::{
+    autopilot::{autopilot_report_system, autopilot_system, toggle_autopilot_system},
+    crosswalk::{spawn_crosswalk, traffic_light_system, TrafficLight},
+    daily::DailyChallenge,
+    dying::player_death_system,
     enemy::{
-        enemy_hit_player_system, move_enemy_system, spawn_enemy_system, Enemy, EnemySpawnConfig,
-        EnemyVelocity,
+        crossing_signal_system, enemy_hit_player_system, move_enemy_system, spawn_enemy_system,
+        spawn_level_crossing, spawn_train_system, Enemy, EnemySpawnConfig, EnemyVelocity,
+        TrafficModifiers, TrafficRng, TrainSchedule, Vehicle,
     },
+    level::{spawn_obstacle, LaneKind, Level},
+    mutators::{Mutator, MutatorSelection, MutatorSet},
+    net::NetConfig,
+    particles::{Burst, EmitParticles},
     player::{
-        move_player_system, player_input_system, spawn_player, Player, PlayerState, PlayerVelocity,
-        Sidewalk,
+        invulnerable_system, move_player_system, player_input_system, spawn_players, GameMode,
+        PlayArea, Player, PlayerMode, PlayerState, PlayerVelocity, Session, Sidewalk,
+        VERSUS_TARGET_CROSSINGS,
     },
-    GameAssets, GameState, COLOR_GRAY, COLOR_RED, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+    scores::HighScores,
+    tilemap::spawn_tilemap,
+    transition::{Effect, Transitions},
+    GameAssets, GameState, SIDE_WALK, WIN_HEIGHT,
 };
 use bevy::prelude::*;
 
@@ -22,9 +33,6 @@ pub struct SideWalkTop;
 #[derive(Component)]
 pub struct SideWalkBottom;
 
-#[derive(Component)]
-pub struct LevelCounter;
-
 pub struct InGamePlugin;
 
 impl Plugin for InGamePlugin {
@@ -32,21 +40,31 @@ impl Plugin for InGamePlugin {
         app.register_type::<PlayerVelocity>()
             .register_type::<Enemy>()
             .register_type::<EnemyVelocity>()
-            .insert_resource(PlayerState::default())
+            .register_type::<Vehicle>()
+            .insert_resource(Session::default())
+            .insert_resource(PlayArea::default())
             .add_system_set(
                 SystemSet::on_enter(GameState::InGame)
                     .with_system(in_game_setup_system)
-                    .with_system(spawn_player),
+                    .with_system(spawn_players),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
                     .with_system(move_player_system)
                     .with_system(player_input_system)
+                    .with_system(toggle_autopilot_system)
+                    .with_system(autopilot_system)
+                    .with_system(autopilot_report_system)
                     .with_system(spawn_enemy_system)
+                    .with_system(spawn_train_system.before(spawn_enemy_system))
+                    .with_system(crossing_signal_system)
+                    .with_system(traffic_light_system.before(move_enemy_system))
                     .with_system(move_enemy_system)
                     .with_system(enemy_hit_player_system)
+                    .with_system(player_death_system)
+                    .with_system(invulnerable_system)
                     .with_system(sidewalk_hit_system)
-                    .with_system(increment_level_system),
+                    .with_system(versus_win_system),
             )
             .add_system_set(
                 SystemSet::on_exit(GameState::InGame).with_system(despawn_in_game_system),
@@ -54,149 +72,175 @@ impl Plugin for InGamePlugin {
     }
 }
 
-fn in_game_setup_system(
+#[allow(clippy::too_many_arguments)]
+pub fn in_game_setup_system(
     mut commands: Commands,
     game_assets: Res<GameAssets>,
-    mut player_state: ResMut<PlayerState>,
+    mut session: ResMut<Session>,
+    daily: Res<DailyChallenge>,
+    mutator_selection: Res<MutatorSelection>,
+    high_scores: Res<HighScores>,
+    level: Res<Level>,
+    net_config: Option<Res<NetConfig>>,
 ) {
-    player_state.alive = true;
-    player_state.level = 1;
-    player_state.last_sidewalk = Sidewalk::None;
+    session.reset();
+
+    // An online race shares only the seed, and every peer has to simulate the same traffic.
+    session.mutators = if !session.game_mode.takes_mutators() || net_config.is_some() {
+        MutatorSet::default()
+    } else {
+        mutator_selection.0
+    };
+
+    session.seed = if session.game_mode == GameMode::Daily {
+        daily.seed
+    } else {
+        rand::random()
+    };
+
+    // Racing the ghost replays its run: same seed, same mutators, so the same traffic.
+    let ghost_run = high_scores
+        .best
+        .as_ref()
+        .filter(|_| session.ghost_race && net_config.is_none());
+    if let Some(best) = ghost_run {
+        session.seed = best.seed;
+        session.mutators = best.mutators;
+    }
 
-    commands.insert_resource(EnemySpawnConfig {
-        timer: Timer::new(Duration::from_secs(2), TimerMode::Repeating),
-    });
+    commands.insert_resource(EnemySpawnConfig::new());
+    let traffic = TrafficModifiers {
+        mirrored: session.mutators.contains(Mutator::Mirrored),
+        ..default()
+    };
+    let traffic_direction = traffic.direction();
+    commands.insert_resource(traffic);
+    commands.insert_resource(TrafficRng::from_seed(session.seed));
+    commands.insert_resource(TrainSchedule::new());
+    commands.insert_resource(TrafficLight::new());
+
+    spawn_tilemap(&mut commands, &level);
+
+    for lane in level.lanes.iter() {
+        let direction = lane.direction * traffic_direction;
+
+        match lane.kind {
+            LaneKind::Rail => spawn_level_crossing(&mut commands, &game_assets, lane.y, direction),
+            LaneKind::Crosswalk => spawn_crosswalk(&mut commands, lane.y, direction),
+            LaneKind::Road | LaneKind::River => {}
+        }
+    }
 
-    commands
-        .spawn(SpriteBundle {
-            texture: game_assets.road.clone(),
-            ..default()
-        })
-        .insert(GameBackground)
-        .insert(Name::new("Road"));
+    for obstacle in level.obstacles.iter() {
+        spawn_obstacle(&mut commands, obstacle);
+    }
 
     commands
-        .spawn(SpriteBundle {
-            sprite: Sprite {
-                color: Color::rgb(COLOR_GRAY.0, COLOR_GRAY.1, COLOR_GRAY.2),
-                custom_size: Some(Vec2::new(WIN_WIDTH, SIDE_WALK)),
-                ..default()
-            },
-            transform: Transform {
-                translation: Vec3 {
-                    x: 0.,
-                    y: -(WIN_HEIGHT + SIDE_WALK) / 2.,
-                    z: 1.,
-                },
-                ..default()
-            },
-            ..default()
-        })
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            0.,
+            -(WIN_HEIGHT + SIDE_WALK) / 2.,
+            1.,
+        )))
         .insert(SideWalkBottom)
         .insert(GameBackground)
         .insert(Name::new("SideWalkBottom"));
 
     commands
-        .spawn(SpriteBundle {
-            sprite: Sprite {
-                color: Color::rgb(COLOR_GRAY.0, COLOR_GRAY.1, COLOR_GRAY.2),
-                custom_size: Some(Vec2::new(WIN_WIDTH, SIDE_WALK)),
-                ..default()
-            },
-            transform: Transform {
-                translation: Vec3 {
-                    x: 0.,
-                    y: (WIN_HEIGHT + SIDE_WALK) / 2.,
-                    z: 1.,
-                },
-                ..default()
-            },
-            ..default()
-        })
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            0.,
+            (WIN_HEIGHT + SIDE_WALK) / 2.,
+            1.,
+        )))
         .insert(SideWalkTop)
         .insert(GameBackground)
-        .insert(Name::new("SideWalkTop"))
-        .with_children(|parent| {
-            parent
-                .spawn(Text2dBundle {
-                    text: Text::from_section(
-                        "Lv: 1",
-                        TextStyle {
-                            font: game_assets.font.clone(),
-                            font_size: 35.,
-                            color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
-                        },
-                    ),
-                    transform: Transform {
-                        translation: Vec3::new((WIN_WIDTH / 2.) - 100., SIDE_WALK / 2., 2.),
-                        ..default()
-                    },
-                    ..default()
-                })
-                .insert(LevelCounter);
-        });
+        .insert(Name::new("SideWalkTop"));
 }
 
-fn sidewalk_hit_system(
-    mut player_state: ResMut<PlayerState>,
-    player_query: Query<&Transform, With<Player>>,
+pub fn sidewalk_hit_system(
+    mut session: ResMut<Session>,
+    mut player_query: Query<(&Transform, &mut PlayerState), With<Player>>,
     mut spawn_timer: ResMut<EnemySpawnConfig>,
+    mut emit: EventWriter<EmitParticles>,
+    transitions: Res<Transitions>,
 ) {
-    if let Ok(player_tf) = player_query.get_single() {
+    // Nothing counts once the run is ending.
+    if transitions.is_running() {
+        return;
+    }
+
+    for (player_tf, mut player_state) in player_query.iter_mut() {
         let player_y_pos = player_tf.translation.y;
-        let next_level = player_state.level + 1;
-        let next_time = 2. - f32::from(next_level) * 0.05;
+        let mut crossed = false;
 
         if player_state.last_sidewalk == Sidewalk::Bottom
             || player_state.last_sidewalk == Sidewalk::None
         {
             if player_y_pos > 300. && player_y_pos < 400. {
                 player_state.last_sidewalk = Sidewalk::Top;
-                player_state.level = next_level;
-
-                spawn_timer
-                    .timer
-                    .set_duration(Duration::from_secs_f32(next_time));
+                crossed = true;
             }
         }
 
         if player_state.last_sidewalk == Sidewalk::Top {
             if player_y_pos < -300. && player_y_pos > -400. {
                 player_state.last_sidewalk = Sidewalk::Bottom;
-                player_state.level = next_level;
+                crossed = true;
+            }
+        }
+
+        if crossed {
+            player_state.level = player_state.level.saturating_add(1);
+            session.combo += 1;
+            emit.send(EmitParticles {
+                burst: Burst::Confetti,
+                position: player_tf.translation.truncate(),
+            });
+        }
+
+        // Traffic difficulty follows whoever is furthest ahead.
+        if player_state.level > session.level {
+            session.level = player_state.level;
 
-                spawn_timer
-                    .timer
-                    .set_duration(Duration::from_secs_f32(next_time));
+            if !spawn_timer.remote_controlled {
+                spawn_timer.set_level(session.level);
             }
         }
     }
 }
 
-fn increment_level_system(
-    player_state: Res<PlayerState>,
-    mut level_query: Query<&mut Text, With<LevelCounter>>,
+fn versus_win_system(
+    mut session: ResMut<Session>,
+    player_query: Query<(&Player, &PlayerState)>,
+    mut transitions: ResMut<Transitions>,
 ) {
-    if let Ok(mut level_text) = level_query.get_single_mut() {
-        level_text.sections[0].value = format!("Lv: {}", player_state.level);
+    if session.mode != PlayerMode::Versus {
+        return;
+    }
+
+    for (player, player_state) in player_query.iter() {
+        if player_state.crossings() >= VERSUS_TARGET_CROSSINGS {
+            session.winner = Some(player.id);
+            transitions.set(GameState::GameOver, Effect::Fade);
+            return;
+        }
     }
 }
 
-fn despawn_in_game_system(
+pub fn despawn_in_game_system(
     mut commands: Commands,
     background_query: Query<Entity, With<GameBackground>>,
-    level_query: Query<Entity, With<LevelCounter>>,
     enemy_query: Query<Entity, With<Enemy>>,
+    player_query: Query<Entity, With<Player>>,
 ) {
     for ent in enemy_query.iter() {
         commands.entity(ent).despawn();
     }
 
-    for ent in background_query.iter() {
+    for ent in player_query.iter() {
         commands.entity(ent).despawn();
     }
 
-    if let Ok(level_ent) = level_query.get_single() {
-        commands.entity(level_ent).despawn_recursive();
+    for ent in background_query.iter() {
+        commands.entity(ent).despawn();
     }
 }
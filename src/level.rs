@@ -0,0 +1,399 @@
+use std::{fs, str};
+
+use crate::{in_game::GameBackground, WIN_HEIGHT};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+
+/// Where the editor saves the level, and where the asset server finds it.
+const LEVEL_FILE: &str = "assets/levels/road.level";
+const LEVEL_ASSET: &str = "levels/road.level";
+
+/// Closest two lanes may be, centre to centre, so a river and its banks still fit.
+pub const LANE_MIN_GAP: f32 = 100.;
+/// Lanes keep clear of the sidewalks by this much.
+pub const LANE_MARGIN: f32 = 50.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LaneKind {
+    Road,
+    /// A road lane with a zebra crossing and a traffic light.
+    Crosswalk,
+    /// Crossed on logs and turtles instead of dodging cars.
+    River,
+    /// A road lane shared with a train running on a timetable.
+    Rail,
+}
+
+pub const LANE_KINDS: [LaneKind; 4] = [
+    LaneKind::Road,
+    LaneKind::Crosswalk,
+    LaneKind::River,
+    LaneKind::Rail,
+];
+
+impl LaneKind {
+    pub fn key(&self) -> &'static str {
+        match self {
+            LaneKind::Road => "road",
+            LaneKind::Crosswalk => "crosswalk",
+            LaneKind::River => "river",
+            LaneKind::Rail => "rail",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        LANE_KINDS.into_iter().find(|kind| kind.key() == key)
+    }
+
+    pub fn carries_cars(&self) -> bool {
+        *self != LaneKind::River
+    }
+
+    pub fn next(&self) -> Self {
+        let index = LANE_KINDS.iter().position(|kind| kind == self).unwrap_or(0);
+        LANE_KINDS[(index + 1) % LANE_KINDS.len()]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Lane {
+    pub y: f32,
+    pub kind: LaneKind,
+    /// 1 when traffic drives right, -1 when it drives left. River platforms drift the
+    /// same way.
+    pub direction: f32,
+    /// How likely a new car is to pick this lane, relative to the other lanes.
+    pub spawn_rate: f32,
+    /// Share of red cars among those spawned here, the rest are green.
+    pub red_share: f32,
+}
+
+impl Lane {
+    pub fn new(y: f32, kind: LaneKind) -> Self {
+        Self {
+            y,
+            kind,
+            direction: 1.,
+            spawn_rate: 1.,
+            red_share: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ObstacleKind {
+    Hydrant,
+    Tree,
+    Bin,
+    /// Low wall between two lanes. Several in a row leave gaps to walk through.
+    Median,
+    Block,
+}
+
+pub const OBSTACLE_KINDS: [ObstacleKind; 5] = [
+    ObstacleKind::Hydrant,
+    ObstacleKind::Tree,
+    ObstacleKind::Bin,
+    ObstacleKind::Median,
+    ObstacleKind::Block,
+];
+
+impl ObstacleKind {
+    pub fn key(&self) -> &'static str {
+        match self {
+            ObstacleKind::Hydrant => "hydrant",
+            ObstacleKind::Tree => "tree",
+            ObstacleKind::Bin => "bin",
+            ObstacleKind::Median => "median",
+            ObstacleKind::Block => "block",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        OBSTACLE_KINDS.into_iter().find(|kind| kind.key() == key)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        match self {
+            ObstacleKind::Hydrant => Vec2::new(20., 26.),
+            ObstacleKind::Tree => Vec2::new(44., 44.),
+            ObstacleKind::Bin => Vec2::new(26., 30.),
+            ObstacleKind::Median => Vec2::new(240., 18.),
+            ObstacleKind::Block => Vec2::new(40., 40.),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            ObstacleKind::Hydrant => Color::rgb(0.8, 0.15, 0.12),
+            ObstacleKind::Tree => Color::rgb(0.18, 0.5, 0.22),
+            ObstacleKind::Bin => Color::rgb(0.3, 0.4, 0.32),
+            ObstacleKind::Median => Color::rgb(0.75, 0.73, 0.68),
+            ObstacleKind::Block => Color::rgb(0.45, 0.42, 0.38),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = OBSTACLE_KINDS
+            .iter()
+            .position(|kind| kind == self)
+            .unwrap_or(0);
+        OBSTACLE_KINDS[(index + 1) % OBSTACLE_KINDS.len()]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct LevelObstacle {
+    pub kind: ObstacleKind,
+    pub position: Vec2,
+}
+
+impl LevelObstacle {
+    pub fn new(kind: ObstacleKind, x: f32, y: f32) -> Self {
+        Self {
+            kind,
+            position: Vec2::new(x, y),
+        }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let half = self.kind.size() / 2.;
+        (point - self.position).abs().cmple(half).all()
+    }
+}
+
+/// Static obstacle that blocks the chicken.
+#[derive(Component)]
+pub struct Obstacle {
+    pub size: Vec2,
+}
+
+/// Layout of the road: its lanes from bottom to top and the obstacles placed on it.
+#[derive(Resource, Clone, TypeUuid)]
+#[uuid = "7cc7f681-f1c4-4c72-90d5-b06c7721f88a"]
+pub struct Level {
+    pub lanes: Vec<Lane>,
+    pub obstacles: Vec<LevelObstacle>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            lanes: vec![
+                Lane::new(-230., LaneKind::Crosswalk),
+                Lane {
+                    direction: -1.,
+                    ..Lane::new(-80., LaneKind::Road)
+                },
+                Lane {
+                    direction: -1.,
+                    ..Lane::new(70., LaneKind::River)
+                },
+                Lane::new(220., LaneKind::Rail),
+            ],
+            obstacles: vec![
+                LevelObstacle::new(ObstacleKind::Hydrant, -300., -370.),
+                LevelObstacle::new(ObstacleKind::Bin, -130., -375.),
+                LevelObstacle::new(ObstacleKind::Tree, 250., -355.),
+                LevelObstacle::new(ObstacleKind::Median, -250., -155.),
+                LevelObstacle::new(ObstacleKind::Median, 250., -155.),
+                LevelObstacle::new(ObstacleKind::Median, -290., 150.),
+                LevelObstacle::new(ObstacleKind::Median, 290., 150.),
+                LevelObstacle::new(ObstacleKind::Tree, -250., 355.),
+                LevelObstacle::new(ObstacleKind::Bin, 120., 370.),
+                LevelObstacle::new(ObstacleKind::Hydrant, 320., 370.),
+            ],
+        }
+    }
+}
+
+/// Handle to the level file, holding on to the last saved level.
+#[derive(Resource)]
+pub struct LevelHandle(pub Handle<Level>);
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = Level::parse(str::from_utf8(bytes)?);
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        // The built-in road is played until the level file has loaded, or if it can't be.
+        app.add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .insert_resource(Level::default())
+            .add_startup_system(load_level_system)
+            .add_system(apply_level_system);
+    }
+}
+
+fn load_level_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LevelHandle(asset_server.load(LEVEL_ASSET)));
+}
+
+/// Plays the level file once it has loaded. The file isn't watched for changes; the editor
+/// writes saved levels into the asset itself, which lands here as a modification.
+fn apply_level_system(
+    mut events: EventReader<AssetEvent<Level>>,
+    levels: Res<Assets<Level>>,
+    mut level: ResMut<Level>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(loaded) = levels.get(handle) {
+                *level = loaded.clone();
+            }
+        }
+    }
+}
+
+impl Level {
+    pub fn save(&self) {
+        if let Err(err) = fs::write(LEVEL_FILE, self.serialize()) {
+            warn!("could not save level: {}", err);
+        }
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut level = Self {
+            lanes: Vec::new(),
+            obstacles: Vec::new(),
+        };
+
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+
+            match fields.next() {
+                Some("lane") => {
+                    let kind = fields.next().and_then(LaneKind::from_key);
+                    let mut numbers = fields.filter_map(|field| field.parse::<f32>().ok());
+                    let y = numbers.next();
+
+                    if let (Some(kind), Some(y)) = (kind, y) {
+                        let defaults = Lane::new(y, kind);
+                        level.lanes.push(Lane {
+                            direction: numbers.next().unwrap_or(1.).signum(),
+                            spawn_rate: numbers.next().unwrap_or(defaults.spawn_rate),
+                            red_share: numbers.next().unwrap_or(defaults.red_share),
+                            ..defaults
+                        });
+                    }
+                }
+                Some("obstacle") => {
+                    let kind = fields.next().and_then(ObstacleKind::from_key);
+                    let x = fields.next().and_then(|field| field.parse().ok());
+                    let y = fields.next().and_then(|field| field.parse().ok());
+
+                    if let (Some(kind), Some(x), Some(y)) = (kind, x, y) {
+                        level.obstacles.push(LevelObstacle::new(kind, x, y));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        level.lanes.sort_by(|a, b| a.y.total_cmp(&b.y));
+        level
+    }
+
+    fn serialize(&self) -> String {
+        let mut contents = String::from("# lane <kind> <y> <direction> <spawn rate> <red share>\n");
+
+        for lane in self.lanes.iter() {
+            contents += &format!(
+                "lane {} {} {} {} {}\n",
+                lane.kind.key(),
+                lane.y,
+                lane.direction,
+                lane.spawn_rate,
+                lane.red_share
+            );
+        }
+
+        if !self.obstacles.is_empty() {
+            contents += "# obstacle <kind> <x> <y>\n";
+        }
+        for obstacle in self.obstacles.iter() {
+            contents += &format!(
+                "obstacle {} {} {}\n",
+                obstacle.kind.key(),
+                obstacle.position.x,
+                obstacle.position.y
+            );
+        }
+
+        contents
+    }
+
+    pub fn lanes_of(&self, kind: LaneKind) -> impl Iterator<Item = &Lane> + '_ {
+        self.lanes.iter().filter(move |lane| lane.kind == kind)
+    }
+
+    pub fn car_lanes(&self) -> impl Iterator<Item = &Lane> + '_ {
+        self.lanes.iter().filter(|lane| lane.kind.carries_cars())
+    }
+
+    pub fn lane_at(&self, y: f32) -> Option<&Lane> {
+        self.lanes.iter().find(|lane| (lane.y - y).abs() < 1.)
+    }
+
+    /// Direction of the traffic in the lane at `y`, as laid out in the level.
+    pub fn direction(&self, y: f32) -> f32 {
+        self.lane_at(y).map_or(1., |lane| lane.direction)
+    }
+
+    /// Whether a lane can go at `y` without crowding another or leaving the road.
+    pub fn has_room(&self, y: f32) -> bool {
+        y.abs() <= WIN_HEIGHT / 2. - LANE_MARGIN
+            && self
+                .lanes
+                .iter()
+                .all(|lane| (lane.y - y).abs() >= LANE_MIN_GAP)
+    }
+
+    pub fn add_lane(&mut self, lane: Lane) {
+        self.lanes.push(lane);
+        self.lanes.sort_by(|a, b| a.y.total_cmp(&b.y));
+    }
+}
+
+pub fn spawn_obstacle(commands: &mut Commands, obstacle: &LevelObstacle) -> Entity {
+    let size = obstacle.kind.size();
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: obstacle.kind.color(),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(obstacle.position.extend(1.4)),
+            ..default()
+        })
+        .insert(Obstacle { size })
+        .insert(GameBackground)
+        .insert(Name::new("Obstacle"))
+        .id()
+}
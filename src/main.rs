@@ -4,11 +4,36 @@ use bevy_inspector_egui::WorldInspectorPlugin;
 #[cfg(target_arch = "wasm32")]
 use bevy_inspector_egui::WorldInspectorParams;
 
+mod animation;
+mod attract;
+mod autopilot;
+mod collectible;
+mod crosswalk;
+mod daily;
+mod dying;
+mod editor;
+mod endless;
 mod enemy;
+mod fairness;
 mod game_over;
+mod ghost;
+mod hud;
 mod in_game;
+mod level;
 mod menu;
+mod modes;
+mod modifiers;
+mod mutators;
+mod net;
+mod particles;
 mod player;
+mod powerup;
+mod protocol;
+mod river;
+mod scores;
+mod tilemap;
+mod transition;
+mod weather;
 
 const WIN_WIDTH: f32 = 820.;
 const WIN_HEIGHT: f32 = 600.;
@@ -29,14 +54,18 @@ pub struct GameAssets {
     player: Handle<TextureAtlas>,
     enemy_red: Handle<Image>,
     enemy_green: Handle<Image>,
-    road: Handle<Image>,
     font: Handle<Font>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum GameState {
     Menu,
+    Attract,
     InGame,
+    Endless,
+    Editor,
+    /// The run is over and the chicken's end is being shown, over the frozen game.
+    Dying,
     GameOver,
 }
 
@@ -44,6 +73,7 @@ fn main() {
     let mut app = App::new();
 
     app.insert_resource(ClearColor(Color::rgb(1., 1., 1.)))
+        .insert_resource(scores::HighScores::load())
         .add_state(GameState::Menu)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             window: WindowDescriptor {
@@ -57,11 +87,33 @@ fn main() {
         }));
 
     app.add_plugin(WorldInspectorPlugin::new())
+        .add_plugin(level::LevelPlugin)
         .add_plugin(menu::MenuPlugin)
+        .add_plugin(attract::AttractPlugin)
         .add_plugin(in_game::InGamePlugin)
         .add_plugin(game_over::GameOverPlugin)
+        .add_plugin(ghost::GhostPlugin)
+        .add_plugin(collectible::CollectiblePlugin)
+        .add_plugin(powerup::PowerUpPlugin)
+        .add_plugin(river::RiverPlugin)
+        .add_plugin(fairness::FairnessPlugin)
+        .add_plugin(endless::EndlessPlugin)
+        .add_plugin(modes::ModesPlugin)
+        .add_plugin(daily::DailyPlugin)
+        .add_plugin(mutators::MutatorsPlugin)
+        .add_plugin(editor::EditorPlugin)
+        .add_plugin(animation::AnimationPlugin)
+        .add_plugin(dying::DyingPlugin)
+        .add_plugin(transition::TransitionPlugin)
+        .add_plugin(particles::ParticlesPlugin)
+        .add_plugin(weather::WeatherPlugin)
+        .add_plugin(hud::HudPlugin)
         .add_startup_system(setup_system);
 
+    if let Some(net_config) = net::NetConfig::from_args() {
+        app.insert_resource(net_config).add_plugin(net::NetPlugin);
+    }
+
     #[cfg(target_arch = "wasm32")]
     app.add_system(disable_inspector_system);
 
@@ -83,7 +135,6 @@ fn setup_system(
     commands.insert_resource(GameAssets {
         enemy_green: asset_server.load("imgs/car_green.png"),
         enemy_red: asset_server.load("imgs/car_red.png"),
-        road: asset_server.load("imgs/road.png"),
         font: asset_server.load("fonts/RubikSprayPaint-Regular.ttf"),
         player,
     });
@@ -1,4 +1,10 @@
-use crate::{GameAssets, GameState, COLOR_RED, COLOR_YELLOW, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH};
+use crate::{
+    mutators::{toggle_color, MutatorSelection, MutatorToggle, MUTATORS},
+    particles::{ParticleSettings, ReducedEffectsLabel},
+    player::{GameMode, PlayerMode, Session},
+    transition::{Effect, Transitions},
+    GameAssets, GameState, COLOR_RED, COLOR_YELLOW, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+};
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
@@ -6,6 +12,13 @@ pub struct MenuPlugin;
 #[derive(Component)]
 pub struct Ancestor;
 
+#[derive(Component)]
+pub struct StartButton {
+    pub players: PlayerMode,
+    pub game: GameMode,
+    pub ghost_race: bool,
+}
+
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(GameState::Menu).with_system(setup_ui_system))
@@ -16,7 +29,12 @@ impl Plugin for MenuPlugin {
     }
 }
 
-fn setup_ui_system(mut commands: Commands, game_assets: Res<GameAssets>) {
+fn setup_ui_system(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mutator_selection: Res<MutatorSelection>,
+    particle_settings: Res<ParticleSettings>,
+) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -61,41 +79,104 @@ fn setup_ui_system(mut commands: Commands, game_assets: Res<GameAssets>) {
                         flex_direction: FlexDirection::Row,
                         justify_content: JustifyContent::Center,
                         align_items: AlignItems::Center,
+                        align_content: AlignContent::Center,
+                        flex_wrap: FlexWrap::Wrap,
                         flex_grow: 1.,
                         ..default()
                     },
                     ..default()
                 })
                 .with_children(|parent| {
-                    parent
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                size: Size::new(Val::Px(250.), Val::Px(65.)),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            background_color: Color::rgb(
-                                COLOR_YELLOW.0,
-                                COLOR_YELLOW.1,
-                                COLOR_YELLOW.2,
-                            )
-                            .into(),
-                            ..default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "START",
-                                TextStyle {
-                                    font: game_assets.font.clone(),
-                                    font_size: 50.,
-                                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                    for (label, players, game, ghost_race) in [
+                        ("START", PlayerMode::Single, GameMode::Classic, false),
+                        ("GHOST", PlayerMode::Single, GameMode::Classic, true),
+                        ("CO-OP", PlayerMode::Coop, GameMode::Classic, false),
+                        ("VERSUS", PlayerMode::Versus, GameMode::Classic, false),
+                        ("TIMED", PlayerMode::Single, GameMode::TimeAttack, false),
+                        ("SPRINT", PlayerMode::Single, GameMode::Sprint, false),
+                        ("SURVIVAL", PlayerMode::Single, GameMode::Survival, false),
+                        ("DAILY", PlayerMode::Single, GameMode::Daily, false),
+                        ("ENDLESS", PlayerMode::Single, GameMode::Endless, false),
+                    ] {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(230.), Val::Px(65.)),
+                                    margin: UiRect::all(Val::Px(10.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
                                 },
-                            ));
-                        });
+                                background_color: Color::rgb(
+                                    COLOR_YELLOW.0,
+                                    COLOR_YELLOW.1,
+                                    COLOR_YELLOW.2,
+                                )
+                                .into(),
+                                ..default()
+                            })
+                            .insert(StartButton {
+                                players,
+                                game,
+                                ghost_race,
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    label,
+                                    TextStyle {
+                                        font: game_assets.font.clone(),
+                                        font_size: 40.,
+                                        color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                                    },
+                                ));
+                            });
+                    }
                 });
             // End of Middle Container
 
+            // Mutators Container
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        flex_wrap: FlexWrap::Wrap,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for mutator in MUTATORS {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(115.), Val::Px(36.)),
+                                    margin: UiRect::all(Val::Px(5.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: toggle_color(
+                                    mutator_selection.0.contains(mutator),
+                                ),
+                                ..default()
+                            })
+                            .insert(MutatorToggle(mutator))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    mutator.label(),
+                                    TextStyle {
+                                        font: game_assets.font.clone(),
+                                        font_size: 16.,
+                                        color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                                    },
+                                ));
+                            });
+                    }
+                });
+            // End of Mutators Container
+
             // Bottom Container
             parent
                 .spawn(NodeBundle {
@@ -167,6 +248,48 @@ fn setup_ui_system(mut commands: Commands, game_assets: Res<GameAssets>) {
                             color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
                         },
                     ));
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "ARROWS -> PLAYER 2",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 25.,
+                            color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+                        },
+                    ));
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "E -> ROAD EDITOR",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 25.,
+                            color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+                        },
+                    ));
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "P -> AUTOPILOT",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: 25.,
+                            color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+                        },
+                    ));
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            particle_settings.label(),
+                            TextStyle {
+                                font: game_assets.font.clone(),
+                                font_size: 25.,
+                                color: Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2),
+                            },
+                        ))
+                        .insert(ReducedEffectsLabel);
                 });
 
             // End of Bottom Container
@@ -175,28 +298,43 @@ fn setup_ui_system(mut commands: Commands, game_assets: Res<GameAssets>) {
 
 pub fn button_control_system(
     kb: Res<Input<KeyCode>>,
-    mut button_query: Query<&Interaction, With<Button>>,
-    mut game_state: ResMut<State<GameState>>,
+    button_query: Query<(&Interaction, &StartButton)>,
+    mut session: ResMut<Session>,
+    mut transitions: ResMut<Transitions>,
     mut windows: ResMut<Windows>,
 ) {
     if kb.pressed(KeyCode::Return) {
-        game_state.set(GameState::InGame).unwrap();
+        session.test_play = false;
+        transitions.set(session.game_mode.state(), Effect::Wipe);
+        return;
     }
 
-    if let Ok(interaction) = button_query.get_single_mut() {
+    let mut hovered = false;
+
+    for (interaction, start_button) in button_query.iter() {
         match *interaction {
             Interaction::Clicked => {
-                game_state.set(GameState::InGame).unwrap();
-            }
-            Interaction::Hovered => {
-                let current_cursor = windows.primary().cursor_icon();
-                if current_cursor != CursorIcon::Hand {
-                    windows.primary_mut().set_cursor_icon(CursorIcon::Hand);
-                }
+                session.mode = start_button.players;
+                session.game_mode = start_button.game;
+                session.ghost_race = start_button.ghost_race;
+                session.test_play = false;
+                transitions.set(start_button.game.state(), Effect::Wipe);
+                return;
             }
+            Interaction::Hovered => hovered = true,
             _ => {}
         }
     }
+
+    let cursor = if hovered {
+        CursorIcon::Hand
+    } else {
+        CursorIcon::Default
+    };
+
+    if windows.primary().cursor_icon() != cursor {
+        windows.primary_mut().set_cursor_icon(cursor);
+    }
 }
 
 pub fn despawn_ui_system(mut commands: Commands, mut node_query: Query<Entity, With<Ancestor>>) {
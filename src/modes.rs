@@ -0,0 +1,149 @@
+use crate::{
+    enemy::TrafficModifiers,
+    game_over::setup_game_over_ui_system,
+    mutators::mutators_summary,
+    net::Lockstep,
+    player::{GameMode, PlayerMode, Session},
+    scores::{HighScores, ModeRecord},
+    transition::{Effect, Transitions},
+    GameState, TIME_STEP,
+};
+use bevy::prelude::*;
+
+pub const TIME_ATTACK_SECS: f32 = 90.;
+pub const SPRINT_CROSSINGS: u8 = 20;
+/// Traffic speed and spawn rate gained per second survived, up to `SURVIVAL_MAX_SCALE`.
+const SURVIVAL_RAMP: f32 = 0.01;
+const SURVIVAL_MAX_SCALE: f32 = 2.5;
+const SURVIVAL_SOURCE: &str = "survival";
+
+pub struct ModesPlugin;
+
+impl Plugin for ModesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(run_clock_system)
+                .with_system(mode_rules_system.after(run_clock_system)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::GameOver)
+                .with_system(save_record_system.before(setup_game_over_ui_system)),
+        );
+    }
+}
+
+pub fn crossings(session: &Session) -> u8 {
+    session.level - 1
+}
+
+/// What a finished run puts on its mode's leaderboard, if it counts at all.
+pub fn run_result(session: &Session) -> Option<f32> {
+    match session.game_mode {
+        // Classic keeps its best run with the ghost, daily runs are kept per day.
+        GameMode::Classic | GameMode::Daily => None,
+        GameMode::TimeAttack => Some(f32::from(crossings(session))),
+        GameMode::Sprint => (crossings(session) >= SPRINT_CROSSINGS).then_some(session.time),
+        GameMode::Survival => Some(session.time),
+        GameMode::Endless => Some(session.score as f32),
+    }
+}
+
+/// Sprints are won on time, everything else on how far the chicken got.
+pub fn lower_is_better(mode: GameMode) -> bool {
+    mode == GameMode::Sprint
+}
+
+pub fn format_result(mode: GameMode, value: f32) -> String {
+    match mode {
+        GameMode::Classic | GameMode::Daily => format!("level {}", value as u32),
+        GameMode::TimeAttack => format!("{} crossings", value as u32),
+        GameMode::Sprint | GameMode::Survival => format!("{:.1} s", value),
+        GameMode::Endless => format!("{} lanes", value as u32),
+    }
+}
+
+fn run_clock_system(
+    mut session: ResMut<Session>,
+    lockstep: Option<Res<Lockstep>>,
+    transitions: Res<Transitions>,
+) {
+    // The run is over once its end is asked for, even while the screen is still fading.
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) || transitions.is_running() {
+        return;
+    }
+
+    session.time += TIME_STEP;
+}
+
+fn mode_rules_system(
+    session: Res<Session>,
+    mut traffic: ResMut<TrafficModifiers>,
+    mut transitions: ResMut<Transitions>,
+) {
+    match session.game_mode {
+        GameMode::TimeAttack if session.time >= TIME_ATTACK_SECS => {
+            transitions.set(GameState::GameOver, Effect::Fade);
+        }
+        GameMode::Sprint if crossings(&session) >= SPRINT_CROSSINGS => {
+            transitions.set(GameState::GameOver, Effect::Fade);
+        }
+        GameMode::Survival => {
+            let scale = (1. + session.time * SURVIVAL_RAMP).min(SURVIVAL_MAX_SCALE);
+            traffic.speed.set(SURVIVAL_SOURCE, scale);
+            traffic.spawn_rate.set(SURVIVAL_SOURCE, scale);
+        }
+        _ => {}
+    }
+}
+
+/// A mode record, with the mutators it was set with.
+pub fn format_record(mode: GameMode, record: &ModeRecord) -> String {
+    let result = format_result(mode, record.value);
+
+    match mutators_summary(record.mutators) {
+        Some(mutators) => format!("{} ({})", result, mutators),
+        None => result,
+    }
+}
+
+/// The run clock as the mode counts it: time left, time taken, or time survived.
+pub fn clock_label(session: &Session) -> String {
+    match session.game_mode {
+        GameMode::TimeAttack => format!("{:.1}", (TIME_ATTACK_SECS - session.time).max(0.)),
+        GameMode::Sprint => format!(
+            "{:.1}  {}/{}",
+            session.time,
+            crossings(session),
+            SPRINT_CROSSINGS
+        ),
+        GameMode::Survival | GameMode::Classic | GameMode::Daily | GameMode::Endless => {
+            format!("{:.1}", session.time)
+        }
+    }
+}
+
+fn save_record_system(mut high_scores: ResMut<HighScores>, mut session: ResMut<Session>) {
+    if session.mode != PlayerMode::Single {
+        return;
+    }
+
+    let Some(value) = run_result(&session) else {
+        return;
+    };
+
+    let record = ModeRecord {
+        value,
+        mutators: session.mutators,
+    };
+
+    session.new_record = high_scores.submit_record(
+        session.game_mode,
+        record,
+        lower_is_better(session.game_mode),
+    );
+
+    if session.new_record {
+        high_scores.save();
+    }
+}
@@ -0,0 +1,22 @@
+use bevy::utils::HashMap;
+
+/// Multiplicative factors keyed by whatever applies them, so effects from different
+/// features stack and can each be removed without knowing about the others.
+#[derive(Default)]
+pub struct Scale {
+    factors: HashMap<&'static str, f32>,
+}
+
+impl Scale {
+    pub fn set(&mut self, source: &'static str, factor: f32) {
+        self.factors.insert(source, factor);
+    }
+
+    pub fn clear(&mut self, source: &'static str) {
+        self.factors.remove(source);
+    }
+
+    pub fn value(&self) -> f32 {
+        self.factors.values().product()
+    }
+}
@@ -0,0 +1,297 @@
+use crate::{
+    enemy::{Enemy, TrafficModifiers, Vehicle},
+    in_game::{in_game_setup_system, GameBackground},
+    menu::StartButton,
+    player::{move_player_system, player_input_system, Player, PlayerVelocity, Session},
+    GameState, COLOR_GRAY, COLOR_YELLOW, SIDE_WALK, TIME_STEP, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::prelude::*;
+
+const DOUBLE_SPEED_SOURCE: &str = "double_speed";
+
+/// Side of the square around the chicken that the fog leaves clear.
+const FOG_CLEARING: f32 = 260.;
+const FOG_ALPHA: f32 = 0.92;
+/// Invisible cars show up for `FLASH_SECS` once every `FLASH_PERIOD_SECS`.
+const FLASH_PERIOD_SECS: f32 = 2.;
+const FLASH_SECS: f32 = 0.3;
+/// How much of the gap to the wanted velocity a slippery chicken closes per second.
+const SLIPPERY_GRIP: f32 = 3.;
+const TINY_SCALE: f32 = 1.;
+/// Opacity of the mutator row when the hovered mode would ignore it.
+const IGNORED_ALPHA: f32 = 0.3;
+
+/// Optional rules that change a run. Any combination can be active at once.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    /// Road traffic drives right to left.
+    Mirrored,
+    Fog,
+    InvisibleCars,
+    Slippery,
+    DoubleSpeed,
+    TinyChicken,
+}
+
+pub const MUTATORS: [Mutator; 6] = [
+    Mutator::Mirrored,
+    Mutator::Fog,
+    Mutator::InvisibleCars,
+    Mutator::Slippery,
+    Mutator::DoubleSpeed,
+    Mutator::TinyChicken,
+];
+
+impl Mutator {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mutator::Mirrored => "MIRROR",
+            Mutator::Fog => "FOG",
+            Mutator::InvisibleCars => "GHOST CARS",
+            Mutator::Slippery => "SLIPPERY",
+            Mutator::DoubleSpeed => "2X SPEED",
+            Mutator::TinyChicken => "TINY",
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << MUTATORS
+            .iter()
+            .position(|mutator| mutator == self)
+            .unwrap_or(0)
+    }
+}
+
+/// The mutators of a run, stored as bits so it can be compared and written to the scores
+/// file as a single number.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MutatorSet(pub u8);
+
+impl MutatorSet {
+    pub fn contains(&self, mutator: Mutator) -> bool {
+        self.0 & mutator.bit() != 0
+    }
+
+    pub fn toggle(&mut self, mutator: Mutator) {
+        self.0 ^= mutator.bit();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Mutator> + '_ {
+        MUTATORS
+            .into_iter()
+            .filter(|mutator| self.contains(*mutator))
+    }
+}
+
+/// Mutators picked on the menu for the next runs.
+#[derive(Resource, Default)]
+pub struct MutatorSelection(pub MutatorSet);
+
+/// Menu button turning one mutator on and off.
+#[derive(Component)]
+pub struct MutatorToggle(pub Mutator);
+
+#[derive(Component)]
+struct FogBank(Vec2);
+
+/// What a slippery chicken is actually doing, as opposed to what it is being told to do.
+#[derive(Component, Default)]
+struct Momentum(Vec2);
+
+pub struct MutatorsPlugin;
+
+impl Plugin for MutatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MutatorSelection::default())
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(mutator_toggle_system)
+                    .with_system(mutator_row_system.after(mutator_toggle_system)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(spawn_fog_system.after(in_game_setup_system)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(apply_mutators_system)
+                    .with_system(
+                        slippery_system
+                            .after(player_input_system)
+                            .before(move_player_system),
+                    )
+                    .with_system(invisible_cars_system)
+                    .with_system(fog_system.after(move_player_system)),
+            );
+    }
+}
+
+pub fn toggle_color(active: bool) -> BackgroundColor {
+    if active {
+        Color::rgb(COLOR_YELLOW.0, COLOR_YELLOW.1, COLOR_YELLOW.2).into()
+    } else {
+        Color::rgb(COLOR_GRAY.0, COLOR_GRAY.1, COLOR_GRAY.2).into()
+    }
+}
+
+fn mutator_toggle_system(
+    mut selection: ResMut<MutatorSelection>,
+    toggle_query: Query<(&Interaction, &MutatorToggle), Changed<Interaction>>,
+) {
+    for (interaction, toggle) in toggle_query.iter() {
+        if *interaction == Interaction::Clicked {
+            selection.0.toggle(toggle.0);
+        }
+    }
+}
+
+/// Greys the mutator row out while a mode that plays without mutators is hovered.
+fn mutator_row_system(
+    selection: Res<MutatorSelection>,
+    start_query: Query<(&Interaction, &StartButton)>,
+    mut toggle_query: Query<(&MutatorToggle, &mut BackgroundColor)>,
+) {
+    let ignored = start_query.iter().any(|(interaction, start_button)| {
+        *interaction == Interaction::Hovered && !start_button.game.takes_mutators()
+    });
+
+    for (toggle, mut color) in toggle_query.iter_mut() {
+        let wanted = if ignored {
+            Color::rgba(COLOR_GRAY.0, COLOR_GRAY.1, COLOR_GRAY.2, IGNORED_ALPHA)
+        } else {
+            toggle_color(selection.0.contains(toggle.0)).0
+        };
+
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+    }
+}
+
+fn spawn_fog_system(mut commands: Commands, session: Res<Session>) {
+    if !session.mutators.contains(Mutator::Fog) {
+        return;
+    }
+
+    // Tall banks either side of the clearing and short ones above and below it, each big
+    // enough to cover the screen wherever the chicken is.
+    let screen = Vec2::new(WIN_WIDTH, WIN_HEIGHT + SIDE_WALK * 2.);
+    let offset = (screen + FOG_CLEARING) / 2.;
+
+    for side in [
+        Vec2::new(-offset.x, 0.),
+        Vec2::new(offset.x, 0.),
+        Vec2::new(0., -offset.y),
+        Vec2::new(0., offset.y),
+    ] {
+        let size = if side.x == 0. {
+            Vec2::new(FOG_CLEARING, screen.y)
+        } else {
+            Vec2::new(screen.x, screen.y * 2.)
+        };
+
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.85, 0.87, 0.9, FOG_ALPHA),
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(side.extend(5.)),
+                ..default()
+            })
+            .insert(FogBank(side))
+            .insert(GameBackground)
+            .insert(Name::new("Fog"));
+    }
+}
+
+/// Keeps the clearing in the fog centred on the first chicken.
+fn fog_system(
+    player_query: Query<(&Player, &Transform), Without<FogBank>>,
+    mut fog_query: Query<(&FogBank, &mut Transform)>,
+) {
+    let Some((_, player_tf)) = player_query.iter().find(|(player, _)| player.id == 0) else {
+        return;
+    };
+
+    for (bank, mut transform) in fog_query.iter_mut() {
+        transform.translation.x = player_tf.translation.x + bank.0.x;
+        transform.translation.y = player_tf.translation.y + bank.0.y;
+    }
+}
+
+fn apply_mutators_system(
+    session: Res<Session>,
+    mut traffic: ResMut<TrafficModifiers>,
+    mut player_query: Query<&mut Transform, With<Player>>,
+) {
+    if session.mutators.contains(Mutator::DoubleSpeed) {
+        traffic.speed.set(DOUBLE_SPEED_SOURCE, 2.);
+    } else {
+        traffic.speed.clear(DOUBLE_SPEED_SOURCE);
+    }
+
+    if session.mutators.contains(Mutator::TinyChicken) {
+        for mut player_tf in player_query.iter_mut() {
+            player_tf.scale = Vec3::splat(TINY_SCALE);
+        }
+    }
+}
+
+/// Lets the chicken's real velocity trail behind the controls, so it slides into moves
+/// and out of stops.
+fn slippery_system(
+    mut commands: Commands,
+    session: Res<Session>,
+    mut player_query: Query<(Entity, &mut PlayerVelocity, Option<&mut Momentum>), With<Player>>,
+) {
+    if !session.mutators.contains(Mutator::Slippery) {
+        return;
+    }
+
+    let grip = (SLIPPERY_GRIP * TIME_STEP).min(1.);
+
+    for (player_ent, mut velocity, momentum) in player_query.iter_mut() {
+        let Some(mut momentum) = momentum else {
+            commands.entity(player_ent).insert(Momentum::default());
+            continue;
+        };
+
+        let wanted = Vec2::new(velocity.x, velocity.y);
+        let mut actual = momentum.0 + (wanted - momentum.0) * grip;
+        if actual.length() < 0.01 {
+            actual = Vec2::ZERO;
+        }
+
+        momentum.0 = actual;
+        velocity.x = actual.x;
+        velocity.y = actual.y;
+    }
+}
+
+/// Cars can only be seen in short flashes.
+fn invisible_cars_system(
+    session: Res<Session>,
+    mut car_query: Query<&mut Visibility, (With<Enemy>, With<Vehicle>)>,
+) {
+    if !session.mutators.contains(Mutator::InvisibleCars) {
+        return;
+    }
+
+    let visible = session.time % FLASH_PERIOD_SECS < FLASH_SECS;
+
+    for mut visibility in car_query.iter_mut() {
+        visibility.is_visible = visible;
+    }
+}
+
+/// One line summary of the mutators of a run.
+pub fn mutators_summary(mutators: MutatorSet) -> Option<String> {
+    if mutators == MutatorSet::default() {
+        return None;
+    }
+
+    let labels: Vec<&str> = mutators.iter().map(|mutator| mutator.label()).collect();
+    Some(labels.join(" + "))
+}
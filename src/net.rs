@@ -0,0 +1,391 @@
+use std::{collections::BTreeMap, net::UdpSocket, time::Duration};
+
+use crate::{
+    animation::idle_sprite_index,
+    enemy::{EnemySpawnConfig, TrafficRng},
+    player::{Direction, Player, PlayerState, Session},
+    protocol::{Packet, DEFAULT_RELAY_ADDR, LEVEL_HISTORY, MAX_PEERS},
+    GameAssets, GameState, COLOR_RED,
+};
+use bevy::{prelude::*, utils::HashMap};
+
+// Ticks between a peer sending its state and everyone needing it to advance.
+const INPUT_DELAY: u32 = 6;
+const HELLO_INTERVAL: u64 = 1;
+const REMOTE_CHICKEN_ALPHA: f32 = 0.4;
+
+#[derive(Resource)]
+pub struct NetConfig {
+    pub relay: String,
+}
+
+impl NetConfig {
+    /// Reads `--connect [relay address]` from the command line.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip_while(|arg| arg != "--connect");
+        args.next()?;
+
+        Some(Self {
+            relay: args
+                .next()
+                .unwrap_or_else(|| DEFAULT_RELAY_ADDR.to_string()),
+        })
+    }
+}
+
+#[derive(Resource)]
+pub struct NetSocket(UdpSocket);
+
+#[derive(Clone, Copy)]
+struct RemoteChickenState {
+    tick: u32,
+    position: Vec2,
+    direction: Direction,
+}
+
+/// Input-delay lockstep: the traffic only advances a tick once every racer's state for the
+/// tick `INPUT_DELAY` frames earlier is known, so all peers spawn and move cars identically.
+#[derive(Resource)]
+pub struct Lockstep {
+    pub started: bool,
+    pub ready: bool,
+    pub local_peer: u8,
+    pub peers: u8,
+    pub tick: u32,
+    /// Level the traffic of every peer is set to.
+    pub applied_level: u8,
+    levels: HashMap<u8, BTreeMap<u32, u8>>,
+    left_at: HashMap<u8, u32>,
+    remote_chickens: HashMap<u8, RemoteChickenState>,
+    hello_timer: Timer,
+}
+
+impl Default for Lockstep {
+    fn default() -> Self {
+        Self {
+            started: false,
+            ready: false,
+            local_peer: 0,
+            peers: 0,
+            tick: 0,
+            applied_level: 1,
+            levels: HashMap::default(),
+            left_at: HashMap::default(),
+            remote_chickens: HashMap::default(),
+            hello_timer: Timer::new(Duration::from_secs(HELLO_INTERVAL), TimerMode::Repeating),
+        }
+    }
+}
+
+impl Lockstep {
+    fn record_level(&mut self, peer: u8, tick: u32, level: u8) {
+        self.levels.entry(peer).or_default().insert(tick, level);
+    }
+
+    fn is_active(&self, peer: u8, tick: u32) -> bool {
+        self.left_at.get(&peer).is_none_or(|left| tick < *left)
+    }
+
+    fn has_levels_for(&self, tick: u32) -> bool {
+        (0..self.peers)
+            .filter(|peer| self.is_active(*peer, tick))
+            .all(|peer| {
+                self.levels
+                    .get(&peer)
+                    .is_some_and(|levels| levels.contains_key(&tick))
+            })
+    }
+
+    fn level_at(&self, tick: u32) -> u8 {
+        (0..self.peers)
+            .filter(|peer| self.is_active(*peer, tick))
+            .filter_map(|peer| self.levels.get(&peer)?.get(&tick).copied())
+            .max()
+            .unwrap_or(1)
+    }
+
+    fn level_history(&self, peer: u8, tick: u32) -> [u8; LEVEL_HISTORY] {
+        let mut history = [0; LEVEL_HISTORY];
+
+        if let Some(levels) = self.levels.get(&peer) {
+            for (offset, slot) in history.iter_mut().rev().enumerate() {
+                if let Some(past) = tick.checked_sub(offset as u32) {
+                    *slot = levels.get(&past).copied().unwrap_or(0);
+                }
+            }
+        }
+
+        history
+    }
+
+    fn prune(&mut self, tick: u32) {
+        let Some(oldest) = tick.checked_sub(INPUT_DELAY + LEVEL_HISTORY as u32) else {
+            return;
+        };
+
+        for levels in self.levels.values_mut() {
+            *levels = levels.split_off(&oldest);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct RemoteChicken {
+    pub peer: u8,
+}
+
+#[derive(Component)]
+struct NetStatusText;
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::InGame).with_system(net_join_system))
+            .add_system_to_stage(CoreStage::PreUpdate, net_receive_system)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                net_tick_system.after(net_receive_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(remote_chicken_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(net_leave_system));
+    }
+}
+
+fn open_socket(relay: &str) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(relay)?;
+    socket.set_nonblocking(true)?;
+    socket.send(&Packet::Hello.encode())?;
+    Ok(socket)
+}
+
+fn net_join_system(mut commands: Commands, config: Res<NetConfig>, game_assets: Res<GameAssets>) {
+    let socket = match open_socket(&config.relay) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("could not reach relay {}: {}", config.relay, err);
+            return;
+        }
+    };
+
+    commands.insert_resource(NetSocket(socket));
+    commands.insert_resource(Lockstep::default());
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "WAITING FOR RACERS",
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: 50.,
+                    color: Color::rgb(COLOR_RED.0, COLOR_RED.1, COLOR_RED.2),
+                },
+            ),
+            transform: Transform::from_xyz(0., 0., 5.),
+            ..default()
+        })
+        .insert(NetStatusText);
+}
+
+fn net_receive_system(
+    mut commands: Commands,
+    socket: Option<Res<NetSocket>>,
+    lockstep: Option<ResMut<Lockstep>>,
+    game_assets: Res<GameAssets>,
+    mut session: ResMut<Session>,
+    status_query: Query<Entity, With<NetStatusText>>,
+) {
+    let (Some(socket), Some(mut lockstep)) = (socket, lockstep) else {
+        return;
+    };
+
+    let mut buffer = [0; 64];
+
+    while let Ok(len) = socket.0.recv(&mut buffer) {
+        match Packet::decode(&buffer[..len]) {
+            Some(Packet::Start { seed, peer, peers }) if !lockstep.started => {
+                lockstep.started = true;
+                lockstep.local_peer = peer;
+                lockstep.peers = peers.min(MAX_PEERS as u8);
+                session.seed = seed;
+                commands.insert_resource(TrafficRng::from_seed(seed));
+
+                for remote in (0..lockstep.peers).filter(|remote| *remote != peer) {
+                    commands
+                        .spawn(SpriteSheetBundle {
+                            texture_atlas: game_assets.player.clone(),
+                            sprite: TextureAtlasSprite {
+                                index: idle_sprite_index(Direction::Up),
+                                color: Color::rgba(1., 1., 1., REMOTE_CHICKEN_ALPHA),
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation: Vec3::new(0., 0., 1.),
+                                scale: Vec3::splat(2.),
+                                ..default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            ..default()
+                        })
+                        .insert(RemoteChicken { peer: remote })
+                        .insert(Name::new(format!("Racer {}", remote + 1)));
+                }
+
+                for ent in status_query.iter() {
+                    commands.entity(ent).despawn();
+                }
+            }
+            Some(Packet::Input {
+                peer,
+                tick,
+                x,
+                y,
+                direction,
+                levels,
+            }) => {
+                for (offset, level) in levels.iter().rev().enumerate() {
+                    if let Some(past) = tick.checked_sub(offset as u32) {
+                        if *level > 0 {
+                            lockstep.record_level(peer, past, *level);
+                        }
+                    }
+                }
+
+                let newer = lockstep
+                    .remote_chickens
+                    .get(&peer)
+                    .is_none_or(|state| state.tick < tick);
+
+                if newer {
+                    lockstep.remote_chickens.insert(
+                        peer,
+                        RemoteChickenState {
+                            tick,
+                            position: Vec2::new(x, y),
+                            direction: Direction::from_byte(direction),
+                        },
+                    );
+                }
+            }
+            Some(Packet::Leave { peer, tick }) => {
+                lockstep.left_at.insert(peer, tick);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn net_tick_system(
+    socket: Option<Res<NetSocket>>,
+    lockstep: Option<ResMut<Lockstep>>,
+    spawn_config: Option<ResMut<EnemySpawnConfig>>,
+    player_query: Query<(&Player, &Transform, &Direction, &PlayerState)>,
+    time: Res<Time>,
+) {
+    let (Some(socket), Some(mut lockstep)) = (socket, lockstep) else {
+        return;
+    };
+
+    if !lockstep.started {
+        lockstep.ready = false;
+        lockstep.hello_timer.tick(time.delta());
+
+        if lockstep.hello_timer.just_finished() {
+            let _ = socket.0.send(&Packet::Hello.encode());
+        }
+        return;
+    }
+
+    let tick = lockstep.tick;
+    let local_peer = lockstep.local_peer;
+
+    if let Some((_, player_tf, direction, player_state)) =
+        player_query.iter().find(|(player, ..)| player.id == 0)
+    {
+        lockstep.record_level(local_peer, tick, player_state.level);
+
+        let packet = Packet::Input {
+            peer: local_peer,
+            tick,
+            x: player_tf.translation.x,
+            y: player_tf.translation.y,
+            direction: direction.to_byte(),
+            levels: lockstep.level_history(local_peer, tick),
+        };
+        let _ = socket.0.send(&packet.encode());
+    }
+
+    let past = tick.checked_sub(INPUT_DELAY);
+    lockstep.ready = past.is_none_or(|past| lockstep.has_levels_for(past));
+
+    if !lockstep.ready {
+        return;
+    }
+
+    let level = past.map_or(1, |past| lockstep.level_at(past));
+
+    if let Some(mut spawn_config) = spawn_config {
+        spawn_config.remote_controlled = true;
+
+        if level != lockstep.applied_level {
+            spawn_config.set_level(level);
+            lockstep.applied_level = level;
+        }
+    }
+
+    lockstep.prune(tick);
+    lockstep.tick += 1;
+}
+
+fn remote_chicken_system(
+    lockstep: Option<Res<Lockstep>>,
+    mut remote_query: Query<(
+        &RemoteChicken,
+        &mut Transform,
+        &mut TextureAtlasSprite,
+        &mut Visibility,
+    )>,
+) {
+    let Some(lockstep) = lockstep else {
+        return;
+    };
+
+    for (remote, mut transform, mut sprite, mut visibility) in remote_query.iter_mut() {
+        let state = lockstep.remote_chickens.get(&remote.peer);
+        let gone = lockstep.left_at.contains_key(&remote.peer);
+
+        visibility.is_visible = state.is_some() && !gone;
+
+        if let Some(state) = state {
+            transform.translation.x = state.position.x;
+            transform.translation.y = state.position.y;
+            sprite.index = idle_sprite_index(state.direction);
+        }
+    }
+}
+
+fn net_leave_system(
+    mut commands: Commands,
+    socket: Option<Res<NetSocket>>,
+    lockstep: Option<Res<Lockstep>>,
+    remote_query: Query<Entity, With<RemoteChicken>>,
+    status_query: Query<Entity, With<NetStatusText>>,
+) {
+    if let (Some(socket), Some(lockstep)) = (socket, lockstep) {
+        let packet = Packet::Leave {
+            peer: lockstep.local_peer,
+            tick: lockstep.tick,
+        };
+        let _ = socket.0.send(&packet.encode());
+    }
+
+    commands.remove_resource::<NetSocket>();
+    commands.remove_resource::<Lockstep>();
+
+    for ent in remote_query.iter().chain(status_query.iter()) {
+        commands.entity(ent).despawn();
+    }
+}
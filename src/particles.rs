@@ -0,0 +1,357 @@
+use crate::{
+    player::{Player, PlayerState, PlayerVelocity},
+    GameState, COLOR_RED, COLOR_YELLOW,
+};
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+/// Particles alive at once. Bursts beyond that are cut short rather than spawning more.
+const POOL_SIZE: usize = 384;
+const PARTICLE_Z: f32 = 5.;
+
+const COLOR_FEATHER: (f32, f32, f32) = (0.96, 0.94, 0.9);
+const COLOR_DUST: (f32, f32, f32) = (0.72, 0.66, 0.55);
+const COLOR_EXHAUST: (f32, f32, f32) = (0.35, 0.35, 0.38);
+const COLOR_CONFETTI_BLUE: (f32, f32, f32) = (0.25, 0.5, 0.95);
+const COLOR_CONFETTI_GREEN: (f32, f32, f32) = (0.3, 0.8, 0.35);
+
+const DUST_INTERVAL: f32 = 0.12;
+const EXHAUST_INTERVAL: f32 = 0.15;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Burst {
+    Feathers,
+    Dust,
+    Exhaust,
+    Confetti,
+}
+
+/// How the particles of a burst look and move.
+struct Preset {
+    count: u32,
+    speed: (f32, f32),
+    /// Directions the particles fly off in, in radians.
+    angle: (f32, f32),
+    lifetime: f32,
+    size: Vec2,
+    colors: &'static [(f32, f32, f32)],
+    alpha: f32,
+    /// Pull downwards, in pixels per second squared.
+    gravity: f32,
+    /// Share of their speed the particles lose per second.
+    drag: f32,
+    spin: f32,
+}
+
+impl Burst {
+    fn preset(&self) -> Preset {
+        use std::f32::consts::{FRAC_PI_2, TAU};
+
+        match self {
+            Burst::Feathers => Preset {
+                count: 14,
+                speed: (60., 180.),
+                angle: (0., TAU),
+                lifetime: 1.1,
+                size: Vec2::new(5., 9.),
+                colors: &[COLOR_FEATHER],
+                alpha: 1.,
+                gravity: 60.,
+                drag: 2.5,
+                spin: 4.,
+            },
+            Burst::Dust => Preset {
+                count: 2,
+                speed: (10., 30.),
+                angle: (0., TAU),
+                lifetime: 0.4,
+                size: Vec2::new(4., 4.),
+                colors: &[COLOR_DUST],
+                alpha: 0.7,
+                gravity: 0.,
+                drag: 3.,
+                spin: 0.,
+            },
+            Burst::Exhaust => Preset {
+                count: 1,
+                speed: (15., 30.),
+                angle: (FRAC_PI_2 - 0.5, FRAC_PI_2 + 0.5),
+                lifetime: 0.8,
+                size: Vec2::new(6., 6.),
+                colors: &[COLOR_EXHAUST],
+                alpha: 0.5,
+                gravity: 0.,
+                drag: 1.,
+                spin: 1.,
+            },
+            Burst::Confetti => Preset {
+                count: 30,
+                speed: (150., 300.),
+                angle: (FRAC_PI_2 - 1., FRAC_PI_2 + 1.),
+                lifetime: 1.5,
+                size: Vec2::new(4., 6.),
+                colors: &[
+                    COLOR_RED,
+                    COLOR_YELLOW,
+                    COLOR_CONFETTI_BLUE,
+                    COLOR_CONFETTI_GREEN,
+                ],
+                alpha: 1.,
+                gravity: 300.,
+                drag: 1.,
+                spin: 8.,
+            },
+        }
+    }
+}
+
+/// Asks for a burst of particles at a point of the world.
+pub struct EmitParticles {
+    pub burst: Burst,
+    pub position: Vec2,
+}
+
+/// Emits a burst at regular intervals from wherever its entity is.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    burst: Burst,
+    /// From the entity, in world units.
+    offset: Vec2,
+    timer: Timer,
+    pub active: bool,
+}
+
+impl ParticleEmitter {
+    pub fn new(burst: Burst, interval: f32, offset: Vec2) -> Self {
+        Self {
+            burst,
+            offset,
+            timer: Timer::from_seconds(interval, TimerMode::Repeating),
+            active: true,
+        }
+    }
+
+    pub fn exhaust(offset: Vec2) -> Self {
+        Self::new(Burst::Exhaust, EXHAUST_INTERVAL, offset)
+    }
+
+    pub fn dust() -> Self {
+        Self::new(Burst::Dust, DUST_INTERVAL, Vec2::ZERO)
+    }
+}
+
+#[derive(Resource)]
+pub struct ParticleSettings {
+    /// No particles at all, for slow machines and anyone bothered by motion.
+    pub reduced: bool,
+}
+
+impl ParticleSettings {
+    pub fn from_args() -> Self {
+        Self {
+            reduced: std::env::args().any(|arg| arg == "--reduced-effects"),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "F -> REDUCED EFFECTS: {}",
+            if self.reduced { "ON" } else { "OFF" }
+        )
+    }
+}
+
+/// Menu line showing the reduced effects setting.
+#[derive(Component)]
+pub struct ReducedEffectsLabel;
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+    alpha: f32,
+    gravity: f32,
+    drag: f32,
+    spin: f32,
+}
+
+/// Sprites kept around for particles, so bursts don't spawn entities.
+#[derive(Resource)]
+struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ParticleSettings::from_args())
+            .add_event::<EmitParticles>()
+            .add_startup_system(particle_pool_setup_system)
+            .add_system(player_dust_system.before(emitter_system))
+            .add_system(emitter_system.before(emit_particles_system))
+            .add_system(emit_particles_system.before(particle_system))
+            .add_system(particle_system)
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(toggle_reduced_effects_system),
+            );
+    }
+}
+
+fn particle_pool_setup_system(mut commands: Commands) {
+    let free = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn(SpriteBundle {
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(Particle {
+                    velocity: Vec2::ZERO,
+                    lifetime: Timer::default(),
+                    alpha: 1.,
+                    gravity: 0.,
+                    drag: 0.,
+                    spin: 0.,
+                })
+                .insert(Name::new("Particle"))
+                .id()
+        })
+        .collect();
+
+    commands.insert_resource(ParticlePool { free });
+}
+
+fn toggle_reduced_effects_system(
+    kb: Res<Input<KeyCode>>,
+    mut settings: ResMut<ParticleSettings>,
+    mut label_query: Query<&mut Text, With<ReducedEffectsLabel>>,
+) {
+    if !kb.just_pressed(KeyCode::F) {
+        return;
+    }
+
+    settings.reduced = !settings.reduced;
+
+    for mut text in label_query.iter_mut() {
+        text.sections[0].value = settings.label();
+    }
+}
+
+/// Chickens kick up dust while they walk.
+fn player_dust_system(
+    mut player_query: Query<(&PlayerVelocity, &PlayerState, &mut ParticleEmitter), With<Player>>,
+) {
+    for (velocity, player_state, mut emitter) in player_query.iter_mut() {
+        emitter.active = player_state.alive && (velocity.x != 0. || velocity.y != 0.);
+    }
+}
+
+fn emitter_system(
+    mut emitter_query: Query<(&GlobalTransform, &mut ParticleEmitter)>,
+    mut emit: EventWriter<EmitParticles>,
+    time: Res<Time>,
+) {
+    for (transform, mut emitter) in emitter_query.iter_mut() {
+        if !emitter.active {
+            continue;
+        }
+
+        emitter.timer.tick(time.delta());
+
+        if emitter.timer.just_finished() {
+            emit.send(EmitParticles {
+                burst: emitter.burst,
+                position: transform.translation().truncate() + emitter.offset,
+            });
+        }
+    }
+}
+
+fn emit_particles_system(
+    mut emit: EventReader<EmitParticles>,
+    mut pool: ResMut<ParticlePool>,
+    mut particle_query: Query<(&mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+    settings: Res<ParticleSettings>,
+) {
+    if settings.reduced {
+        emit.clear();
+        return;
+    }
+
+    // Particles are only for show and stay out of the seeded traffic generator.
+    let mut rng = thread_rng();
+
+    for event in emit.iter() {
+        let preset = event.burst.preset();
+
+        for _ in 0..preset.count {
+            let Some(ent) = pool.free.pop() else {
+                return;
+            };
+            let Ok((mut particle, mut transform, mut sprite, mut visibility)) =
+                particle_query.get_mut(ent)
+            else {
+                continue;
+            };
+
+            let angle = rng.gen_range(preset.angle.0..=preset.angle.1);
+            let speed = rng.gen_range(preset.speed.0..=preset.speed.1);
+            let (r, g, b) = preset.colors[rng.gen_range(0..preset.colors.len())];
+
+            *particle = Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                lifetime: Timer::from_seconds(preset.lifetime, TimerMode::Once),
+                alpha: preset.alpha,
+                gravity: preset.gravity,
+                drag: preset.drag,
+                spin: rng.gen_range(-preset.spin..=preset.spin),
+            };
+            *transform = Transform {
+                translation: event.position.extend(PARTICLE_Z),
+                rotation: Quat::from_rotation_z(angle),
+                ..default()
+            };
+            sprite.color = Color::rgba(r, g, b, preset.alpha);
+            sprite.custom_size = Some(preset.size);
+            visibility.is_visible = true;
+        }
+    }
+}
+
+fn particle_system(
+    mut pool: ResMut<ParticlePool>,
+    mut particle_query: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (ent, mut particle, mut transform, mut sprite, mut visibility) in particle_query.iter_mut()
+    {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        particle.lifetime.tick(time.delta());
+
+        if particle.lifetime.finished() {
+            visibility.is_visible = false;
+            pool.free.push(ent);
+            continue;
+        }
+
+        let drag = (1. - particle.drag * dt).max(0.);
+        particle.velocity = particle.velocity * drag - Vec2::Y * particle.gravity * dt;
+        transform.translation += particle.velocity.extend(0.) * dt;
+        transform.rotate_z(particle.spin * dt);
+        sprite
+            .color
+            .set_a(particle.alpha * particle.lifetime.percent_left());
+    }
+}
@@ -1,24 +1,35 @@
-use crate::{GameAssets, BASE_SPEED, PLAYER_DIM, SIDE_WALK, TIME_STEP, WIN_HEIGHT, WIN_WIDTH};
-use bevy::prelude::*;
+use std::time::Duration;
 
-const PLAYER_RIGHT_SPRITE_INDEX: (usize, usize) = (0, 5);
-const PLAYER_UP_SPRITE_INDEX: (usize, usize) = (6, 11);
-const PLAYER_LEFT_SPRITE_INDEX: (usize, usize) = (12, 17);
-const PLAYER_DOWN_SPRITE_INDEX: (usize, usize) = (18, 23);
+use crate::{
+    animation::{idle_sprite_index, Animator, Clip},
+    autopilot::Autopilot,
+    level::Obstacle,
+    modifiers::Scale,
+    mutators::MutatorSet,
+    net::Lockstep,
+    particles::ParticleEmitter,
+    GameAssets, GameState, BASE_SPEED, PLAYER_DIM, SIDE_WALK, TIME_STEP, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
 
-const ARENA_LEFT: f32 = -WIN_WIDTH / 2. + PLAYER_DIM;
-const ARENA_RIGHT: f32 = WIN_WIDTH / 2. - PLAYER_DIM;
+pub const ARENA_LEFT: f32 = -WIN_WIDTH / 2. + PLAYER_DIM;
+pub const ARENA_RIGHT: f32 = WIN_WIDTH / 2. - PLAYER_DIM;
 const ARENA_TOP: f32 = (WIN_HEIGHT + SIDE_WALK * 2.) / 2. - PLAYER_DIM;
 const ARENA_BOTTOM: f32 = -(WIN_HEIGHT + SIDE_WALK * 2.) / 2. + PLAYER_DIM;
 
-fn get_sprite_index(dim: (usize, usize), current_index: usize) -> usize {
-    let index = if current_index >= dim.0 && current_index < dim.1 {
-        current_index + 1
-    } else {
-        dim.0
-    };
-    return index;
-}
+pub const PLAYER_START_Y: f32 = (-WIN_HEIGHT - SIDE_WALK + PLAYER_DIM) / 2.;
+const PLAYER_SPACING: f32 = 60.;
+const PLAYER_TWO_TINT: (f32, f32, f32) = (0.55, 0.8, 1.);
+
+const COOP_LIVES: u8 = 3;
+/// Golden eggs stop adding lives past this many.
+pub const MAX_LIVES: u8 = 9;
+pub const VERSUS_TARGET_CROSSINGS: u8 = 10;
+
+const STICK_DEADZONE: f32 = 0.5;
+
+const INVULNERABLE_SECS: u64 = 1;
+const INVULNERABLE_BLINK_RATE: f32 = 12.;
 
 #[derive(PartialEq, Eq)]
 pub enum Sidewalk {
@@ -27,7 +38,164 @@ pub enum Sidewalk {
     None,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMode {
+    Single,
+    Coop,
+    Versus,
+}
+
+impl PlayerMode {
+    pub fn player_count(&self) -> usize {
+        match self {
+            PlayerMode::Single => 1,
+            PlayerMode::Coop | PlayerMode::Versus => 2,
+        }
+    }
+
+    pub fn starting_lives(&self) -> u8 {
+        match self {
+            PlayerMode::Single => 1,
+            PlayerMode::Coop => COOP_LIVES,
+            PlayerMode::Versus => 0,
+        }
+    }
+}
+
+/// What a run is played for, independent of how many chickens take part.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    Classic,
+    /// As many crossings as possible before the clock runs out.
+    TimeAttack,
+    /// A fixed number of crossings, as fast as possible.
+    Sprint,
+    /// Traffic keeps speeding up for as long as the chicken lives.
+    Survival,
+    /// Same road and twists for everyone on a given day, scored once.
+    Daily,
+    Endless,
+}
+
+impl GameMode {
+    pub fn state(&self) -> GameState {
+        match self {
+            GameMode::Endless => GameState::Endless,
+            _ => GameState::InGame,
+        }
+    }
+
+    /// The daily road is the same for everyone, mutators included, and endless roads are
+    /// built as they go with none.
+    pub fn takes_mutators(&self) -> bool {
+        !matches!(self, GameMode::Daily | GameMode::Endless)
+    }
+
+    /// Name of the mode in the scores file.
+    pub fn key(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Sprint => "sprint",
+            GameMode::Survival => "survival",
+            GameMode::Daily => "daily",
+            GameMode::Endless => "endless",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [
+            GameMode::Classic,
+            GameMode::TimeAttack,
+            GameMode::Sprint,
+            GameMode::Survival,
+            GameMode::Daily,
+            GameMode::Endless,
+        ]
+        .into_iter()
+        .find(|mode| mode.key() == key)
+    }
+}
+
+/// State shared by everyone in the current run.
+#[derive(Resource)]
+pub struct Session {
+    pub mode: PlayerMode,
+    pub game_mode: GameMode,
+    pub lives: u8,
+    pub level: u8,
+    pub winner: Option<usize>,
+    /// Seed of the traffic for the current run.
+    pub seed: u64,
+    pub score: u32,
+    pub corn: u32,
+    /// Crossings in a row without getting hit.
+    pub combo: u32,
+    /// Seconds of play in the current run.
+    pub time: f32,
+    pub mutators: MutatorSet,
+    /// The run beat the record of its mode.
+    pub new_record: bool,
+    /// Run started from the road editor. Nothing it does is recorded.
+    pub test_play: bool,
+    /// Classic run against the ghost of the best run, on that run's road.
+    pub ghost_race: bool,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            mode: PlayerMode::Single,
+            game_mode: GameMode::Classic,
+            lives: PlayerMode::Single.starting_lives(),
+            level: 1,
+            winner: None,
+            seed: 0,
+            score: 0,
+            corn: 0,
+            combo: 0,
+            time: 0.,
+            mutators: MutatorSet::default(),
+            new_record: false,
+            test_play: false,
+            ghost_race: false,
+        }
+    }
+}
+
+impl Session {
+    pub fn reset(&mut self) {
+        self.lives = self.mode.starting_lives();
+        self.level = 1;
+        self.winner = None;
+        self.score = 0;
+        self.corn = 0;
+        self.combo = 0;
+        self.time = 0.;
+        self.new_record = false;
+    }
+}
+
+/// Vertical band the chickens can walk in. Fixed to the screen unless the camera scrolls.
 #[derive(Resource)]
+pub struct PlayArea {
+    pub bottom: f32,
+    pub top: f32,
+    /// Where chickens are sent back to when they lose a life.
+    pub respawn_y: f32,
+}
+
+impl Default for PlayArea {
+    fn default() -> Self {
+        Self {
+            bottom: ARENA_BOTTOM,
+            top: ARENA_TOP,
+            respawn_y: PLAYER_START_Y,
+        }
+    }
+}
+
+#[derive(Component)]
 pub struct PlayerState {
     pub alive: bool,
     pub level: u8,
@@ -44,6 +212,12 @@ impl Default for PlayerState {
     }
 }
 
+impl PlayerState {
+    pub fn crossings(&self) -> u8 {
+        self.level - 1
+    }
+}
+
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
 pub struct PlayerVelocity {
@@ -51,7 +225,7 @@ pub struct PlayerVelocity {
     pub y: f32,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Down,
@@ -59,21 +233,130 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Direction::Down,
+            2 => Direction::Left,
+            3 => Direction::Right,
+            _ => Direction::Up,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Player {
+    pub id: usize,
+}
+
+#[derive(Component, Default)]
+pub struct PlayerModifiers {
+    pub speed: Scale,
+}
+
+/// Grace period after a shield absorbs a hit, while the chicken is still inside the car.
+#[derive(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+}
+
+impl Default for Invulnerable {
+    fn default() -> Self {
+        Self {
+            timer: Timer::new(Duration::from_secs(INVULNERABLE_SECS), TimerMode::Once),
+        }
+    }
+}
+
 #[derive(Component)]
-pub struct Player;
+pub struct PlayerControls {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    /// Position of this player's pad among the connected gamepads.
+    pub gamepad: usize,
+}
+
+impl PlayerControls {
+    pub fn for_player(id: usize) -> Self {
+        match id {
+            0 => Self {
+                up: KeyCode::W,
+                down: KeyCode::S,
+                left: KeyCode::A,
+                right: KeyCode::D,
+                gamepad: 0,
+            },
+            _ => Self {
+                up: KeyCode::Up,
+                down: KeyCode::Down,
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                gamepad: id,
+            },
+        }
+    }
+}
+
+pub fn player_start_x(id: usize, mode: PlayerMode) -> f32 {
+    if mode.player_count() == 1 {
+        0.
+    } else if id == 0 {
+        -PLAYER_SPACING
+    } else {
+        PLAYER_SPACING
+    }
+}
+
+pub fn spawn_players(
+    mut commands: Commands,
+    game_textures: Res<GameAssets>,
+    session: Res<Session>,
+) {
+    for id in 0..session.mode.player_count() {
+        spawn_player_entity(
+            &mut commands,
+            &game_textures,
+            id,
+            player_start_x(id, session.mode),
+        );
+    }
+}
+
+pub fn spawn_player_entity(
+    commands: &mut Commands,
+    game_textures: &GameAssets,
+    id: usize,
+    x: f32,
+) -> Entity {
+    let color = if id == 0 {
+        Color::WHITE
+    } else {
+        Color::rgb(PLAYER_TWO_TINT.0, PLAYER_TWO_TINT.1, PLAYER_TWO_TINT.2)
+    };
 
-pub fn spawn_player(mut commands: Commands, game_textures: Res<GameAssets>) {
     commands
         .spawn(SpriteSheetBundle {
             texture_atlas: game_textures.player.clone(),
             sprite: TextureAtlasSprite {
-                index: PLAYER_UP_SPRITE_INDEX.0,
+                index: idle_sprite_index(Direction::Up),
+                color,
                 ..default()
             },
             transform: Transform {
                 translation: Vec3 {
-                    x: 0.,
-                    y: (-WIN_HEIGHT - SIDE_WALK + PLAYER_DIM) / 2.,
+                    x,
+                    y: PLAYER_START_Y,
                     z: 1.,
                 },
                 scale: Vec3 {
@@ -86,9 +369,72 @@ pub fn spawn_player(mut commands: Commands, game_textures: Res<GameAssets>) {
             ..default()
         })
         .insert(PlayerVelocity::default())
-        .insert(Player)
+        .insert(Player { id })
+        .insert(PlayerState::default())
+        .insert(PlayerControls::for_player(id))
+        .insert(PlayerModifiers::default())
         .insert(Direction::Up)
-        .insert(Name::new("Player"));
+        .insert(Animator::default())
+        .insert(ParticleEmitter::dust())
+        .insert(Name::new(format!("Player {}", id + 1)))
+        .id()
+}
+
+/// Costs the run a life and sends the chicken back to `respawn_y`, or kills it when none are
+/// left. Returns whether the game is over, which it is once the chicken is dead.
+pub fn lose_life(
+    player: &Player,
+    player_tf: &mut Transform,
+    player_state: &mut PlayerState,
+    animator: &mut Animator,
+    session: &mut Session,
+    respawn_y: f32,
+) -> bool {
+    if !player_state.alive {
+        return true;
+    }
+
+    session.combo = 0;
+
+    if session.mode != PlayerMode::Versus {
+        session.lives = session.lives.saturating_sub(1);
+    }
+
+    if session.mode == PlayerMode::Versus || session.lives > 0 {
+        player_tf.translation.x = player_start_x(player.id, session.mode);
+        player_tf.translation.y = respawn_y;
+        player_state.last_sidewalk = Sidewalk::None;
+        animator.play(Clip::Hit);
+        return false;
+    }
+
+    player_state.alive = false;
+    animator.play(Clip::Death);
+    true
+}
+
+/// Where a chicken of half size `half` moving in a straight line from `from` to `to` ends up:
+/// flush against the first obstacle in its way. Obstacles it already overlaps don't hold
+/// it back, so it can always walk out of one.
+pub fn blocked_position(from: Vec2, to: Vec2, half: Vec2, obstacles: &[(Vec2, Vec2)]) -> Vec2 {
+    let mut position = to;
+
+    for (center, size) in obstacles {
+        let reach = half + *size / 2.;
+        let overlaps = |point: Vec2| (point - *center).abs().cmplt(reach).all();
+
+        if !overlaps(position) || overlaps(from) {
+            continue;
+        }
+
+        if to.x != from.x {
+            position.x = center.x - reach.x * (to.x - from.x).signum();
+        } else {
+            position.y = center.y - reach.y * (to.y - from.y).signum();
+        }
+    }
+
+    position
 }
 
 pub fn move_player_system(
@@ -96,51 +442,125 @@ pub fn move_player_system(
         (
             &mut Transform,
             &PlayerVelocity,
-            &mut TextureAtlasSprite,
-            &Direction,
+            &PlayerModifiers,
+            &PlayerState,
         ),
         With<Player>,
     >,
+    obstacle_query: Query<(&Transform, &Obstacle), Without<Player>>,
+    play_area: Res<PlayArea>,
+    lockstep: Option<Res<Lockstep>>,
     //time: Res<Time>,
 ) {
-    if let Ok((mut transform, velocity, mut sprite, direction)) = player_query.get_single_mut() {
-        if velocity.x != 0. || velocity.y != 0. {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    let obstacles: Vec<(Vec2, Vec2)> = obstacle_query
+        .iter()
+        .map(|(obstacle_tf, obstacle)| (obstacle_tf.translation.truncate(), obstacle.size))
+        .collect();
+
+    for (mut transform, velocity, modifiers, player_state) in player_query.iter_mut() {
+        if player_state.alive && (velocity.x != 0. || velocity.y != 0.) {
+            let half = PLAYER_DIM * transform.scale.xy() / 2.;
             let translation = &mut transform.translation;
+            let speed = modifiers.speed.value() * TIME_STEP * BASE_SPEED;
 
-            let new_player_x_position = translation.x + velocity.x * TIME_STEP * BASE_SPEED;
-            let new_player_y_position = translation.y + velocity.y * TIME_STEP * BASE_SPEED;
+            // One axis at a time, so the chicken slides along an obstacle it walks into
+            // at an angle.
+            let from = translation.truncate();
+            let new_player_x_position =
+                (from.x + velocity.x * speed).clamp(ARENA_LEFT, ARENA_RIGHT);
+            let moved_x = blocked_position(
+                from,
+                Vec2::new(new_player_x_position, from.y),
+                half,
+                &obstacles,
+            );
+
+            let new_player_y_position =
+                (moved_x.y + velocity.y * speed).clamp(play_area.bottom, play_area.top);
+            let moved = blocked_position(
+                moved_x,
+                Vec2::new(moved_x.x, new_player_y_position),
+                half,
+                &obstacles,
+            );
+
+            translation.x = moved.x;
+            translation.y = moved.y;
+        }
+    }
+}
 
-            translation.x = new_player_x_position.clamp(ARENA_LEFT, ARENA_RIGHT);
-            translation.y = new_player_y_position.clamp(ARENA_BOTTOM, ARENA_TOP);
+pub fn invulnerable_system(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Invulnerable, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (player_ent, mut invulnerable, mut sprite) in player_query.iter_mut() {
+        invulnerable.timer.tick(time.delta());
 
-            sprite.index = match direction {
-                Direction::Up => get_sprite_index(PLAYER_UP_SPRITE_INDEX, sprite.index),
-                Direction::Down => get_sprite_index(PLAYER_DOWN_SPRITE_INDEX, sprite.index),
-                Direction::Left => get_sprite_index(PLAYER_LEFT_SPRITE_INDEX, sprite.index),
-                Direction::Right => get_sprite_index(PLAYER_RIGHT_SPRITE_INDEX, sprite.index),
-            }
+        if invulnerable.timer.finished() {
+            sprite.color.set_a(1.);
+            commands.entity(player_ent).remove::<Invulnerable>();
+        } else {
+            let blink = (invulnerable.timer.elapsed_secs() * INVULNERABLE_BLINK_RATE) as u32;
+            sprite
+                .color
+                .set_a(if blink.is_multiple_of(2) { 0.3 } else { 1. });
         }
     }
 }
 
 pub fn player_input_system(
     kb: Res<Input<KeyCode>>,
-    mut player_query: Query<(&mut PlayerVelocity, &mut Direction), With<Player>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    mut player_query: Query<
+        (&PlayerControls, &mut PlayerVelocity, &mut Direction),
+        Without<Autopilot>,
+    >,
 ) {
-    if let Ok((mut velocity, mut direction)) = player_query.get_single_mut() {
-        if kb.pressed(KeyCode::A) {
+    for (controls, mut velocity, mut direction) in player_query.iter_mut() {
+        let pad = gamepads.iter().nth(controls.gamepad);
+        let pad_pressed = |button_type: GamepadButtonType| {
+            pad.is_some_and(|pad| pad_buttons.pressed(GamepadButton::new(pad, button_type)))
+        };
+        let pad_axis = |axis_type: GamepadAxisType| {
+            pad.and_then(|pad| pad_axes.get(GamepadAxis::new(pad, axis_type)))
+                .unwrap_or(0.)
+        };
+        let stick_x = pad_axis(GamepadAxisType::LeftStickX);
+        let stick_y = pad_axis(GamepadAxisType::LeftStickY);
+
+        if kb.pressed(controls.left)
+            || pad_pressed(GamepadButtonType::DPadLeft)
+            || stick_x < -STICK_DEADZONE
+        {
             velocity.x = -1.;
             velocity.y = 0.;
             *direction = Direction::Left;
-        } else if kb.pressed(KeyCode::D) {
+        } else if kb.pressed(controls.right)
+            || pad_pressed(GamepadButtonType::DPadRight)
+            || stick_x > STICK_DEADZONE
+        {
             velocity.x = 1.;
             velocity.y = 0.;
             *direction = Direction::Right;
-        } else if kb.pressed(KeyCode::W) {
+        } else if kb.pressed(controls.up)
+            || pad_pressed(GamepadButtonType::DPadUp)
+            || stick_y > STICK_DEADZONE
+        {
             velocity.y = 1.;
             velocity.x = 0.;
             *direction = Direction::Up;
-        } else if kb.pressed(KeyCode::S) {
+        } else if kb.pressed(controls.down)
+            || pad_pressed(GamepadButtonType::DPadDown)
+            || stick_y < -STICK_DEADZONE
+        {
             velocity.y = -1.;
             velocity.x = 0.;
             *direction = Direction::Down;
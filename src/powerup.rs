@@ -0,0 +1,220 @@
+use crate::{
+    enemy::TrafficModifiers,
+    player::{Player, PlayerModifiers},
+    GameState,
+};
+use bevy::prelude::*;
+
+const SLOW_MOTION_SCALE: f32 = 0.5;
+const SPEED_BOOST_SCALE: f32 = 1.6;
+const FREEZE_MAX_SECS: f32 = 9.;
+const SHIELD_MAX_CHARGES: u8 = 3;
+
+const SLOW_MOTION_SOURCE: &str = "slow_motion";
+const SPEED_BOOST_SOURCE: &str = "speed_boost";
+const FREEZE_SOURCE: &str = "freeze";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    SlowMotion,
+    Shield,
+    SpeedBoost,
+    Freeze,
+}
+
+pub const POWER_UP_KINDS: [PowerUpKind; 4] = [
+    PowerUpKind::SlowMotion,
+    PowerUpKind::Shield,
+    PowerUpKind::SpeedBoost,
+    PowerUpKind::Freeze,
+];
+
+/// What picking up an effect that is already running does.
+enum StackRule {
+    /// Restart the countdown.
+    Refresh,
+    /// Add the full duration on top of what is left, up to a cap.
+    Extend { max_secs: f32 },
+    /// Add a charge and restart the countdown.
+    Charges { max: u8 },
+}
+
+impl PowerUpKind {
+    fn duration(&self) -> f32 {
+        match self {
+            PowerUpKind::SlowMotion => 5.,
+            PowerUpKind::Shield => 10.,
+            PowerUpKind::SpeedBoost => 5.,
+            PowerUpKind::Freeze => 3.,
+        }
+    }
+
+    fn rule(&self) -> StackRule {
+        match self {
+            PowerUpKind::SlowMotion | PowerUpKind::SpeedBoost => StackRule::Refresh,
+            PowerUpKind::Freeze => StackRule::Extend {
+                max_secs: FREEZE_MAX_SECS,
+            },
+            PowerUpKind::Shield => StackRule::Charges {
+                max: SHIELD_MAX_CHARGES,
+            },
+        }
+    }
+
+    /// Shield and speed boost only help whoever picked them up; the others act on traffic.
+    fn per_player(&self) -> bool {
+        matches!(self, PowerUpKind::Shield | PowerUpKind::SpeedBoost)
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::SlowMotion => Color::rgb(0.42, 0.3, 0.9),
+            PowerUpKind::Shield => Color::rgb(0.1, 0.6, 0.95),
+            PowerUpKind::SpeedBoost => Color::rgb(0.2, 0.8, 0.3),
+            PowerUpKind::Freeze => Color::rgb(0.6, 0.9, 1.),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::SlowMotion => "SLOW",
+            PowerUpKind::Shield => "SHIELD",
+            PowerUpKind::SpeedBoost => "SPEED",
+            PowerUpKind::Freeze => "FREEZE",
+        }
+    }
+}
+
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub owner: Option<usize>,
+    pub timer: Timer,
+    pub charges: u8,
+}
+
+#[derive(Resource, Default)]
+pub struct ActiveEffects {
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    pub fn grant(&mut self, kind: PowerUpKind, player: usize) {
+        let owner = kind.per_player().then_some(player);
+        let existing = self
+            .effects
+            .iter_mut()
+            .find(|effect| effect.kind == kind && effect.owner == owner);
+
+        let Some(effect) = existing else {
+            self.effects.push(ActiveEffect {
+                kind,
+                owner,
+                timer: Timer::from_seconds(kind.duration(), TimerMode::Once),
+                charges: 1,
+            });
+            return;
+        };
+
+        match kind.rule() {
+            StackRule::Refresh => effect.timer.reset(),
+            StackRule::Extend { max_secs } => {
+                let total = (effect.timer.remaining_secs() + kind.duration()).min(max_secs);
+                effect.timer = Timer::from_seconds(total, TimerMode::Once);
+            }
+            StackRule::Charges { max } => {
+                effect.charges = (effect.charges + 1).min(max);
+                effect.timer.reset();
+            }
+        }
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind, owner: Option<usize>) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.kind == kind && effect.owner == owner)
+    }
+
+    /// Spends one shield charge of `player`, if any. Returns whether the hit was absorbed.
+    pub fn absorb_hit(&mut self, player: usize) -> bool {
+        let Some(index) = self
+            .effects
+            .iter()
+            .position(|effect| effect.kind == PowerUpKind::Shield && effect.owner == Some(player))
+        else {
+            return false;
+        };
+
+        let shield = &mut self.effects[index];
+        shield.charges -= 1;
+        if shield.charges == 0 {
+            self.effects.remove(index);
+        }
+
+        true
+    }
+
+    /// Longest time left on any running effect of this kind.
+    pub fn remaining_secs(&self, kind: PowerUpKind) -> Option<f32> {
+        self.effects
+            .iter()
+            .filter(|effect| effect.kind == kind)
+            .map(|effect| effect.timer.remaining_secs())
+            .reduce(f32::max)
+    }
+}
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActiveEffects::default())
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(tick_effects_system)
+                    .with_system(apply_effects_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(clear_effects_system),
+            );
+    }
+}
+
+fn tick_effects_system(mut effects: ResMut<ActiveEffects>, time: Res<Time>) {
+    for effect in effects.effects.iter_mut() {
+        effect.timer.tick(time.delta());
+    }
+
+    effects.effects.retain(|effect| !effect.timer.finished());
+}
+
+fn apply_effects_system(
+    effects: Res<ActiveEffects>,
+    mut traffic: ResMut<TrafficModifiers>,
+    mut player_query: Query<(&Player, &mut PlayerModifiers)>,
+) {
+    if effects.is_active(PowerUpKind::SlowMotion, None) {
+        traffic.speed.set(SLOW_MOTION_SOURCE, SLOW_MOTION_SCALE);
+    } else {
+        traffic.speed.clear(SLOW_MOTION_SOURCE);
+    }
+
+    if effects.is_active(PowerUpKind::Freeze, None) {
+        traffic.spawn_rate.set(FREEZE_SOURCE, 0.);
+    } else {
+        traffic.spawn_rate.clear(FREEZE_SOURCE);
+    }
+
+    for (player, mut modifiers) in player_query.iter_mut() {
+        if effects.is_active(PowerUpKind::SpeedBoost, Some(player.id)) {
+            modifiers.speed.set(SPEED_BOOST_SOURCE, SPEED_BOOST_SCALE);
+        } else {
+            modifiers.speed.clear(SPEED_BOOST_SOURCE);
+        }
+    }
+}
+
+fn clear_effects_system(mut effects: ResMut<ActiveEffects>, mut traffic: ResMut<TrafficModifiers>) {
+    effects.effects.clear();
+    traffic.speed.clear(SLOW_MOTION_SOURCE);
+    traffic.spawn_rate.clear(FREEZE_SOURCE);
+}
@@ -0,0 +1,95 @@
+pub const DEFAULT_RELAY_ADDR: &str = "127.0.0.1:7777";
+pub const MAX_PEERS: usize = 4;
+
+/// How many past ticks of levels every input packet repeats, so a few lost packets don't
+/// stall the lockstep.
+pub const LEVEL_HISTORY: usize = 8;
+
+const HELLO: u8 = 0;
+const START: u8 = 1;
+const INPUT: u8 = 2;
+const LEAVE: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    Hello,
+    Start {
+        seed: u64,
+        peer: u8,
+        peers: u8,
+    },
+    Input {
+        peer: u8,
+        tick: u32,
+        x: f32,
+        y: f32,
+        direction: u8,
+        /// Levels for ticks `tick + 1 - LEVEL_HISTORY ..= tick`, zero when unknown.
+        levels: [u8; LEVEL_HISTORY],
+    },
+    Leave {
+        peer: u8,
+        tick: u32,
+    },
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        match self {
+            Packet::Hello => bytes.push(HELLO),
+            Packet::Start { seed, peer, peers } => {
+                bytes.extend([START, *peer, *peers]);
+                bytes.extend(seed.to_le_bytes());
+            }
+            Packet::Input {
+                peer,
+                tick,
+                x,
+                y,
+                direction,
+                levels,
+            } => {
+                bytes.extend([INPUT, *peer]);
+                bytes.extend(tick.to_le_bytes());
+                bytes.extend(x.to_le_bytes());
+                bytes.extend(y.to_le_bytes());
+                bytes.push(*direction);
+                bytes.extend(levels);
+            }
+            Packet::Leave { peer, tick } => {
+                bytes.extend([LEAVE, *peer]);
+                bytes.extend(tick.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Packet> {
+        let (kind, body) = bytes.split_first()?;
+
+        match *kind {
+            HELLO => Some(Packet::Hello),
+            START if body.len() == 10 => Some(Packet::Start {
+                peer: body[0],
+                peers: body[1],
+                seed: u64::from_le_bytes(body[2..10].try_into().ok()?),
+            }),
+            INPUT if body.len() == 14 + LEVEL_HISTORY => Some(Packet::Input {
+                peer: body[0],
+                tick: u32::from_le_bytes(body[1..5].try_into().ok()?),
+                x: f32::from_le_bytes(body[5..9].try_into().ok()?),
+                y: f32::from_le_bytes(body[9..13].try_into().ok()?),
+                direction: body[13],
+                levels: body[14..].try_into().ok()?,
+            }),
+            LEAVE if body.len() == 5 => Some(Packet::Leave {
+                peer: body[0],
+                tick: u32::from_le_bytes(body[1..5].try_into().ok()?),
+            }),
+            _ => None,
+        }
+    }
+}
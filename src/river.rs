@@ -0,0 +1,411 @@
+use std::time::Duration;
+
+use crate::{
+    animation::Animator,
+    enemy::TrafficModifiers,
+    level::{LaneKind, Level},
+    net::Lockstep,
+    player::{lose_life, Player, PlayerState, Session, ARENA_LEFT, ARENA_RIGHT, PLAYER_START_Y},
+    GameState, BASE_SPEED, TIME_STEP, WIN_WIDTH,
+};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub const RIVER_HALF_HEIGHT: f32 = 45.;
+/// Platforms drift slower than the cars, in the direction of their lane.
+pub const RIVER_SPEED: f32 = 0.35;
+
+const PLATFORM_HEIGHT: f32 = 50.;
+const LOG_SEGMENT: f32 = 48.;
+const LOG_SEGMENTS: (u32, u32) = (2, 5);
+const TURTLE_SIZE: f32 = 44.;
+const TURTLES_PER_GROUP: (u32, u32) = (2, 4);
+const TURTLE_CHANCE: f64 = 0.35;
+pub const PLATFORM_GAP: (f32, f32) = (70., 150.);
+/// Mixed into the run seed so the river has its own stream, independent of the order in
+/// which cars and platforms happen to be spawned within a frame.
+const RIVER_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+const TURTLE_SURFACED_SECS: f32 = 3.;
+const TURTLE_SINKING_SECS: f32 = 0.8;
+const TURTLE_UNDER_SECS: f32 = 1.4;
+const TURTLE_SINKING_BLINK_RATE: f32 = 8.;
+
+const COLOR_LOG: (f32, f32, f32) = (0.5, 0.33, 0.18);
+const COLOR_TURTLE: (f32, f32, f32) = (0.2, 0.55, 0.3);
+
+/// Something the chicken can stand on while crossing the river.
+#[derive(Component)]
+pub struct Platform {
+    pub width: f32,
+    pub velocity_x: f32,
+}
+
+/// Turtles dive on a fixed cycle; while under water they cannot be stood on.
+#[derive(Component)]
+pub struct Turtle {
+    pub cycle: Timer,
+}
+
+impl Turtle {
+    fn new(offset: f32) -> Self {
+        let mut cycle = Timer::from_seconds(
+            TURTLE_SURFACED_SECS + TURTLE_SINKING_SECS + TURTLE_UNDER_SECS,
+            TimerMode::Repeating,
+        );
+        cycle.set_elapsed(Duration::from_secs_f32(offset));
+
+        Self { cycle }
+    }
+
+    pub fn surfaced(&self) -> bool {
+        self.secs_until_dive() > 0.
+    }
+
+    /// Seconds left before the turtles go under, zero while they are submerged.
+    pub fn secs_until_dive(&self) -> f32 {
+        (TURTLE_SURFACED_SECS + TURTLE_SINKING_SECS - self.cycle.elapsed_secs()).max(0.)
+    }
+
+    fn sinking(&self) -> bool {
+        self.surfaced() && self.cycle.elapsed_secs() > TURTLE_SURFACED_SECS
+    }
+}
+
+struct RiverLane {
+    y: f32,
+    velocity_x: f32,
+    /// Time left until the next platform enters the river at the upstream edge.
+    next_in: f32,
+}
+
+#[derive(Resource)]
+pub struct RiverSpawner {
+    lanes: Vec<RiverLane>,
+    rng: StdRng,
+}
+
+/// The river lane the chicken is standing in, if any.
+pub fn river_at(level: &Level, y: f32) -> Option<f32> {
+    river_lane_at(level.lanes_of(LaneKind::River).map(|lane| lane.y), y)
+}
+
+/// Of the river lanes at `lane_ys`, the one `y` is in.
+pub fn river_lane_at(mut lane_ys: impl Iterator<Item = f32>, y: f32) -> Option<f32> {
+    lane_ys.find(|lane_y| (y - lane_y).abs() < RIVER_HALF_HEIGHT)
+}
+
+pub fn platforms_in<'a>(
+    lane_y: f32,
+    platforms: impl Iterator<Item = (&'a Transform, &'a Platform, Option<&'a Turtle>)>,
+) -> impl Iterator<Item = (&'a Transform, &'a Platform, Option<&'a Turtle>)> {
+    platforms.filter(move |(platform_tf, ..)| (platform_tf.translation.y - lane_y).abs() < 1.)
+}
+
+/// The surfaced platform under the chicken's feet, if any.
+pub fn platform_under<'a>(
+    player_tf: &Transform,
+    platforms: impl Iterator<Item = (&'a Transform, &'a Platform, Option<&'a Turtle>)>,
+) -> Option<&'a Platform> {
+    platforms
+        .filter(|(_, _, turtle)| turtle.is_none_or(|turtle| turtle.surfaced()))
+        .find(|(platform_tf, platform, _)| {
+            (player_tf.translation.x - platform_tf.translation.x).abs() <= platform.width / 2.
+        })
+        .map(|(_, platform, _)| platform)
+}
+
+pub struct RiverPlugin;
+
+impl Plugin for RiverPlugin {
+    fn build(&self, app: &mut App) {
+        for state in [GameState::InGame, GameState::Attract] {
+            app.add_system_set(
+                SystemSet::on_update(state.clone())
+                    .with_system(spawn_platform_system)
+                    .with_system(move_platform_system)
+                    .with_system(turtle_dive_system)
+                    .with_system(ride_platform_system),
+            )
+            .add_system_set(SystemSet::on_exit(state).with_system(despawn_river_system));
+        }
+
+        app.add_system_set(SystemSet::on_update(GameState::InGame).with_system(drown_system));
+    }
+}
+
+/// Seconds a platform drifting at `velocity_x` takes to cover `distance`.
+fn drift_secs(distance: f32, velocity_x: f32) -> f32 {
+    distance / (velocity_x.abs() * BASE_SPEED)
+}
+
+/// Rolls a log or a group of turtles. Returns its width and, for turtles, their dive cycle.
+pub fn random_platform(rng: &mut StdRng) -> (f32, Option<Turtle>) {
+    if rng.gen_bool(TURTLE_CHANCE) {
+        let count = rng.gen_range(TURTLES_PER_GROUP.0..=TURTLES_PER_GROUP.1);
+        let cycle = TURTLE_SURFACED_SECS + TURTLE_SINKING_SECS + TURTLE_UNDER_SECS;
+
+        (
+            count as f32 * TURTLE_SIZE,
+            Some(Turtle::new(rng.gen_range(0. ..cycle))),
+        )
+    } else {
+        let segments = rng.gen_range(LOG_SEGMENTS.0..=LOG_SEGMENTS.1);
+        (segments as f32 * LOG_SEGMENT, None)
+    }
+}
+
+pub fn spawn_platform(
+    commands: &mut Commands,
+    position: Vec2,
+    width: f32,
+    velocity_x: f32,
+    turtle: Option<Turtle>,
+) -> Entity {
+    let color = if turtle.is_some() {
+        COLOR_TURTLE
+    } else {
+        COLOR_LOG
+    };
+
+    let mut platform = commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(color.0, color.1, color.2),
+            custom_size: Some(Vec2::new(width, PLATFORM_HEIGHT)),
+            ..default()
+        },
+        transform: Transform::from_translation(position.extend(0.8)),
+        ..default()
+    });
+
+    platform.insert(Platform { width, velocity_x });
+
+    match turtle {
+        Some(turtle) => platform.insert(turtle).insert(Name::new("Turtles")),
+        None => platform.insert(Name::new("Log")),
+    };
+
+    platform.id()
+}
+
+/// Spawns a random platform on the lane at `y` with its downstream edge `from` past the
+/// upstream edge of the screen. Returns its width.
+fn spawn_river_platform(
+    commands: &mut Commands,
+    rng: &mut StdRng,
+    y: f32,
+    velocity_x: f32,
+    from: f32,
+) -> f32 {
+    let (width, turtle) = random_platform(rng);
+    let upstream = -velocity_x.signum() * WIN_WIDTH / 2.;
+    let x = upstream + velocity_x.signum() * (from - width / 2.);
+    spawn_platform(commands, Vec2::new(x, y), width, velocity_x, turtle);
+
+    width
+}
+
+/// Covers the river lane at `y` with platforms, from the downstream edge of the screen back
+/// to the upstream one. Returns the seconds until the next platform is due.
+pub fn fill_river_lane(commands: &mut Commands, rng: &mut StdRng, y: f32, velocity_x: f32) -> f32 {
+    let mut from = WIN_WIDTH;
+    while from > 0. {
+        let width = spawn_river_platform(commands, rng, y, velocity_x, from);
+        from -= width + rng.gen_range(PLATFORM_GAP.0..PLATFORM_GAP.1);
+    }
+
+    drift_secs(-from, velocity_x)
+}
+
+/// Sends the next platform into the river lane at `y` from the upstream edge. Returns the
+/// seconds until the one after it is due.
+pub fn spawn_next_platform(
+    commands: &mut Commands,
+    rng: &mut StdRng,
+    y: f32,
+    velocity_x: f32,
+) -> f32 {
+    let width = spawn_river_platform(commands, rng, y, velocity_x, 0.);
+    let gap = rng.gen_range(PLATFORM_GAP.0..PLATFORM_GAP.1);
+
+    drift_secs(width + gap, velocity_x)
+}
+
+fn spawn_platform_system(
+    mut commands: Commands,
+    spawner: Option<ResMut<RiverSpawner>>,
+    session: Res<Session>,
+    traffic: Res<TrafficModifiers>,
+    level: Res<Level>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    // First tick of the run: fill the river so the first crossing doesn't wait on an empty
+    // stream. This waits for the traffic seed, which an online race only knows once started.
+    let Some(mut spawner) = spawner else {
+        let mut rng = StdRng::seed_from_u64(session.seed ^ RIVER_SEED_SALT);
+        let mut lanes = Vec::new();
+
+        for lane in level.lanes_of(LaneKind::River) {
+            let velocity_x = RIVER_SPEED * lane.direction;
+            let next_in = fill_river_lane(&mut commands, &mut rng, lane.y, velocity_x);
+
+            lanes.push(RiverLane {
+                y: lane.y,
+                velocity_x,
+                next_in,
+            });
+        }
+
+        commands.insert_resource(RiverSpawner { lanes, rng });
+        return;
+    };
+
+    let spawner = &mut *spawner;
+    for lane in spawner.lanes.iter_mut() {
+        lane.next_in -= TIME_STEP * traffic.speed.value();
+
+        if lane.next_in > 0. {
+            continue;
+        }
+
+        lane.next_in +=
+            spawn_next_platform(&mut commands, &mut spawner.rng, lane.y, lane.velocity_x);
+    }
+}
+
+pub fn move_platform_system(
+    mut commands: Commands,
+    mut platform_query: Query<(Entity, &mut Transform, &Platform)>,
+    traffic: Res<TrafficModifiers>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    let speed_scale = traffic.speed.value();
+
+    for (platform_ent, mut transform, platform) in platform_query.iter_mut() {
+        transform.translation.x += platform.velocity_x * speed_scale * TIME_STEP * BASE_SPEED;
+
+        let downstream = platform.velocity_x.signum();
+        if transform.translation.x * downstream - platform.width / 2. > WIN_WIDTH / 2. {
+            commands.entity(platform_ent).despawn();
+        }
+    }
+}
+
+pub fn turtle_dive_system(
+    mut turtle_query: Query<(&mut Turtle, &mut Sprite)>,
+    traffic: Res<TrafficModifiers>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    for (mut turtle, mut sprite) in turtle_query.iter_mut() {
+        turtle
+            .cycle
+            .tick(Duration::from_secs_f32(TIME_STEP * traffic.speed.value()));
+
+        let alpha = if !turtle.surfaced() {
+            0.2
+        } else if turtle.sinking() {
+            let blink = (turtle.cycle.elapsed_secs() * TURTLE_SINKING_BLINK_RATE) as u32;
+            if blink.is_multiple_of(2) {
+                0.5
+            } else {
+                1.
+            }
+        } else {
+            1.
+        };
+        sprite.color.set_a(alpha);
+    }
+}
+
+/// Moves a chicken in the river lane at `river_y` along with the platform it stands on.
+pub fn ride_platform<'a>(
+    player_tf: &mut Transform,
+    river_y: f32,
+    platforms: impl Iterator<Item = (&'a Transform, &'a Platform, Option<&'a Turtle>)>,
+    speed_scale: f32,
+) {
+    if let Some(platform) = platform_under(player_tf, platforms_in(river_y, platforms)) {
+        let drift = platform.velocity_x * speed_scale * TIME_STEP * BASE_SPEED;
+        player_tf.translation.x = (player_tf.translation.x + drift).clamp(ARENA_LEFT, ARENA_RIGHT);
+    }
+}
+
+/// Chickens standing on a platform drift along with it.
+fn ride_platform_system(
+    mut player_query: Query<&mut Transform, With<Player>>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>), Without<Player>>,
+    traffic: Res<TrafficModifiers>,
+    level: Res<Level>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    for mut player_tf in player_query.iter_mut() {
+        let Some(river_y) = river_at(&level, player_tf.translation.y) else {
+            continue;
+        };
+
+        ride_platform(
+            &mut player_tf,
+            river_y,
+            platform_query.iter(),
+            traffic.speed.value(),
+        );
+    }
+}
+
+fn drown_system(
+    mut player_query: Query<(&Player, &mut Transform, &mut PlayerState, &mut Animator)>,
+    platform_query: Query<(&Transform, &Platform, Option<&Turtle>), Without<Player>>,
+    mut session: ResMut<Session>,
+    level: Res<Level>,
+    lockstep: Option<Res<Lockstep>>,
+) {
+    if lockstep.is_some_and(|lockstep| !lockstep.ready) {
+        return;
+    }
+
+    for (player, mut player_tf, mut player_state, mut animator) in player_query.iter_mut() {
+        let Some(river_y) = river_at(&level, player_tf.translation.y) else {
+            continue;
+        };
+
+        if platform_under(&player_tf, platforms_in(river_y, platform_query.iter())).is_some() {
+            continue;
+        }
+
+        // A shield keeps cars off, not water out, so drowning always costs a life.
+        if lose_life(
+            player,
+            &mut player_tf,
+            &mut player_state,
+            &mut animator,
+            &mut session,
+            PLAYER_START_Y,
+        ) {
+            return;
+        }
+    }
+}
+
+fn despawn_river_system(mut commands: Commands, platform_query: Query<Entity, With<Platform>>) {
+    for ent in platform_query.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    commands.remove_resource::<RiverSpawner>();
+}
@@ -0,0 +1,187 @@
+use std::fs;
+
+use crate::{
+    mutators::MutatorSet,
+    player::{Direction, GameMode},
+};
+use bevy::{prelude::*, utils::HashMap};
+
+const SCORES_FILE: &str = "chicken_road_scores.txt";
+
+#[derive(Clone, Copy)]
+pub struct GhostFrame {
+    pub position: Vec2,
+    pub direction: Direction,
+}
+
+#[derive(Clone)]
+pub struct ScoreEntry {
+    pub level: u8,
+    pub seed: u64,
+    pub mutators: MutatorSet,
+    pub ghost: Vec<GhostFrame>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ModeRecord {
+    pub value: f32,
+    pub mutators: MutatorSet,
+}
+
+#[derive(Resource, Default)]
+pub struct HighScores {
+    pub best: Option<ScoreEntry>,
+    /// Best result of each mode other than classic, whose best run is kept in `best`. Every
+    /// combination of mutators has its own record, so easier runs don't compete with harder
+    /// ones.
+    pub records: HashMap<(GameMode, MutatorSet), ModeRecord>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        match fs::read_to_string(SCORES_FILE) {
+            Ok(contents) => Self::parse(&contents),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = fs::write(SCORES_FILE, self.serialize()) {
+            warn!("could not save high scores: {}", err);
+        }
+    }
+
+    /// Records `entry` if it beats the current best. Returns whether it did.
+    pub fn submit(&mut self, entry: ScoreEntry) -> bool {
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|best| best.level >= entry.level)
+        {
+            return false;
+        }
+
+        self.best = Some(entry);
+        true
+    }
+
+    pub fn record(&self, mode: GameMode, mutators: MutatorSet) -> Option<&ModeRecord> {
+        self.records.get(&(mode, mutators))
+    }
+
+    /// Records `record` as the best result of `mode` with its mutators if it beats the
+    /// current one. Returns whether it did.
+    pub fn submit_record(
+        &mut self,
+        mode: GameMode,
+        record: ModeRecord,
+        lower_is_better: bool,
+    ) -> bool {
+        let beaten = match self.record(mode, record.mutators) {
+            Some(best) if lower_is_better => record.value < best.value,
+            Some(best) => record.value > best.value,
+            None => true,
+        };
+
+        if beaten {
+            self.records.insert((mode, record.mutators), record);
+        }
+        beaten
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut scores = Self::default();
+
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+
+            match fields.next() {
+                Some("best") => {
+                    let level = fields.next().and_then(|field| field.parse().ok());
+                    let seed = fields.next().and_then(|field| field.parse().ok());
+                    // Runs saved before mutators existed had none.
+                    let mutators = fields.next().and_then(|field| field.parse().ok());
+
+                    if let (Some(level), Some(seed)) = (level, seed) {
+                        scores.best = Some(ScoreEntry {
+                            level,
+                            seed,
+                            mutators: MutatorSet(mutators.unwrap_or(0)),
+                            ghost: Vec::new(),
+                        });
+                    }
+                }
+                Some("ghost") => {
+                    if let Some(best) = &mut scores.best {
+                        best.ghost = fields.filter_map(parse_ghost_frame).collect();
+                    }
+                }
+                Some("record") => {
+                    let mode = fields.next().and_then(GameMode::from_key);
+                    let value = fields.next().and_then(|field| field.parse().ok());
+                    let mutators = fields.next().and_then(|field| field.parse().ok());
+
+                    if let (Some(mode), Some(value)) = (mode, value) {
+                        let mutators = MutatorSet(mutators.unwrap_or(0));
+                        scores
+                            .records
+                            .insert((mode, mutators), ModeRecord { value, mutators });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        scores
+    }
+
+    fn serialize(&self) -> String {
+        let mut contents = String::new();
+
+        if let Some(best) = &self.best {
+            let ghost: Vec<String> = best
+                .ghost
+                .iter()
+                .map(|frame| {
+                    format!(
+                        "{:.1},{:.1},{}",
+                        frame.position.x,
+                        frame.position.y,
+                        frame.direction.to_byte()
+                    )
+                })
+                .collect();
+
+            contents += &format!(
+                "best {} {} {}\nghost {}\n",
+                best.level,
+                best.seed,
+                best.mutators.0,
+                ghost.join(" ")
+            );
+        }
+
+        for ((mode, _), record) in self.records.iter() {
+            contents += &format!(
+                "record {} {} {}\n",
+                mode.key(),
+                record.value,
+                record.mutators.0
+            );
+        }
+
+        contents
+    }
+}
+
+fn parse_ghost_frame(field: &str) -> Option<GhostFrame> {
+    let mut parts = field.split(',');
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    let direction = parts.next()?.parse().ok()?;
+
+    Some(GhostFrame {
+        position: Vec2::new(x, y),
+        direction: Direction::from_byte(direction),
+    })
+}
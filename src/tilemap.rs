@@ -0,0 +1,259 @@
+use crate::{
+    enemy::RAIL_GAUGE,
+    in_game::GameBackground,
+    level::{Lane, LaneKind, Level},
+    river::RIVER_HALF_HEIGHT,
+    COLOR_GRAY, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::prelude::*;
+
+/// Tiles are as fine as the editor grid so lanes always start on a tile edge.
+pub const TILE: f32 = 10.;
+const COLUMNS: usize = (WIN_WIDTH / TILE) as usize;
+const ROWS: usize = ((WIN_HEIGHT + 2. * SIDE_WALK) / TILE) as usize;
+/// Rows farther than this from every lane are left as grass.
+const BAND_HALF: f32 = 100.;
+/// Ballast reaches this far past the rails.
+const BALLAST_MARGIN: f32 = 8.;
+/// Dash pattern, in tiles: lane dividers are long, road edges short.
+const LANE_DASH: usize = 4;
+const EDGE_DASH: usize = 3;
+const LANE_DASH_HEIGHT: f32 = 4.;
+const EDGE_DASH_HEIGHT: f32 = 6.;
+pub const RAIL_HEIGHT: f32 = 6.;
+
+pub const COLOR_ASPHALT: (f32, f32, f32) = (0.58, 0.58, 0.58);
+pub const COLOR_GRASS: (f32, f32, f32) = (0.36, 0.58, 0.3);
+const COLOR_KERB: (f32, f32, f32) = (0.45, 0.45, 0.45);
+pub const COLOR_WATER: (f32, f32, f32) = (0.2, 0.45, 0.8);
+pub const COLOR_BALLAST: (f32, f32, f32) = (0.47, 0.42, 0.37);
+pub const COLOR_RAIL: (f32, f32, f32) = (0.3, 0.3, 0.3);
+const COLOR_EDGE_DASH: (f32, f32, f32) = (0.95, 0.8, 0.1);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Grass,
+    Sidewalk,
+    Kerb,
+    Asphalt,
+    /// Asphalt with a white dash dividing two lanes.
+    LaneDash,
+    /// Asphalt with a yellow dash where the road ends.
+    EdgeDash,
+    Water,
+    Ballast,
+    /// Ballast with a rail across it.
+    Rail,
+}
+
+impl Tile {
+    /// The surface under whatever is painted on the tile.
+    fn ground(&self) -> Tile {
+        match self {
+            Tile::LaneDash | Tile::EdgeDash => Tile::Asphalt,
+            Tile::Rail => Tile::Ballast,
+            tile => *tile,
+        }
+    }
+
+    fn is_road(&self) -> bool {
+        matches!(self.ground(), Tile::Asphalt | Tile::Ballast)
+    }
+
+    fn color(&self) -> Color {
+        let (r, g, b) = match self.ground() {
+            Tile::Grass => COLOR_GRASS,
+            Tile::Sidewalk => COLOR_GRAY,
+            Tile::Kerb => COLOR_KERB,
+            Tile::Water => COLOR_WATER,
+            Tile::Ballast => COLOR_BALLAST,
+            _ => COLOR_ASPHALT,
+        };
+        Color::rgb(r, g, b)
+    }
+
+    /// Paint drawn over the ground, and how tall it is.
+    fn marking(&self) -> Option<(Color, f32)> {
+        match self {
+            Tile::LaneDash => Some((Color::WHITE, LANE_DASH_HEIGHT)),
+            Tile::EdgeDash => Some((
+                Color::rgb(COLOR_EDGE_DASH.0, COLOR_EDGE_DASH.1, COLOR_EDGE_DASH.2),
+                EDGE_DASH_HEIGHT,
+            )),
+            Tile::Rail => Some((
+                Color::rgb(COLOR_RAIL.0, COLOR_RAIL.1, COLOR_RAIL.2),
+                RAIL_HEIGHT,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The ground of the whole play field, sidewalks included, row by row from the bottom.
+struct TileMap {
+    rows: Vec<[Tile; COLUMNS]>,
+}
+
+impl TileMap {
+    fn from_level(level: &Level) -> Self {
+        let bands: Vec<Option<&Lane>> = (0..ROWS).map(|row| band(level, row_y(row))).collect();
+
+        let grounds: Vec<Tile> = (0..ROWS)
+            .map(|row| ground(row_y(row), bands[row]))
+            .collect();
+
+        let rows = (0..ROWS)
+            .map(|row| {
+                let ground = grounds[row];
+                let below = row.checked_sub(1).map(|below| grounds[below]);
+                let above = grounds.get(row + 1).copied();
+
+                let pattern = if ground != Tile::Asphalt {
+                    None
+                } else if !below.is_some_and(|tile| tile.is_road())
+                    || !above.is_some_and(|tile| tile.is_road())
+                {
+                    Some(Tile::EdgeDash)
+                } else if bands[row] != bands[row - 1] {
+                    Some(Tile::LaneDash)
+                } else {
+                    None
+                };
+
+                let mut tiles = [ground; COLUMNS];
+                for (column, tile) in tiles.iter_mut().enumerate() {
+                    *tile = match pattern {
+                        Some(Tile::EdgeDash) if (column / EDGE_DASH).is_multiple_of(2) => {
+                            Tile::EdgeDash
+                        }
+                        Some(Tile::LaneDash) if (column / LANE_DASH).is_multiple_of(2) => {
+                            Tile::LaneDash
+                        }
+                        _ => ground,
+                    };
+                }
+                tiles
+            })
+            .collect();
+
+        Self { rows }
+    }
+}
+
+fn row_y(row: usize) -> f32 {
+    -(WIN_HEIGHT / 2. + SIDE_WALK) + (row as f32 + 0.5) * TILE
+}
+
+fn column_x(column: usize) -> f32 {
+    -WIN_WIDTH / 2. + column as f32 * TILE
+}
+
+/// The lane a row of road belongs to, if any is close enough.
+fn band(level: &Level, y: f32) -> Option<&Lane> {
+    if y.abs() > WIN_HEIGHT / 2. {
+        return None;
+    }
+
+    level
+        .lanes
+        .iter()
+        .filter(|lane| (lane.y - y).abs() <= BAND_HALF)
+        .min_by(|a, b| (a.y - y).abs().total_cmp(&(b.y - y).abs()))
+}
+
+fn ground(y: f32, lane: Option<&Lane>) -> Tile {
+    if y.abs() > WIN_HEIGHT / 2. {
+        // The row touching the road is the kerb.
+        return if y.abs() < WIN_HEIGHT / 2. + TILE {
+            Tile::Kerb
+        } else {
+            Tile::Sidewalk
+        };
+    }
+
+    let Some(lane) = lane else {
+        return Tile::Grass;
+    };
+
+    match lane.kind {
+        LaneKind::Road | LaneKind::Crosswalk => Tile::Asphalt,
+        LaneKind::River if (y - lane.y).abs() < RIVER_HALF_HEIGHT + TILE / 2. => Tile::Water,
+        LaneKind::River => Tile::Grass,
+        LaneKind::Rail => {
+            let on_rail = [lane.y - RAIL_GAUGE / 2., lane.y + RAIL_GAUGE / 2.]
+                .iter()
+                .any(|rail_y| *rail_y >= y - TILE / 2. && *rail_y < y + TILE / 2.);
+
+            if on_rail {
+                Tile::Rail
+            } else if (y - lane.y).abs() <= RAIL_GAUGE / 2. + BALLAST_MARGIN {
+                Tile::Ballast
+            } else {
+                Tile::Asphalt
+            }
+        }
+    }
+}
+
+/// Spawns the ground for `level`. Neighbouring tiles that look the same are drawn as a
+/// single sprite, so a plain row of asphalt costs one sprite rather than a row of them.
+pub fn spawn_tilemap(commands: &mut Commands, level: &Level) -> Vec<Entity> {
+    let map = TileMap::from_level(level);
+    let mut spawned = Vec::new();
+
+    for (row, tiles) in map.rows.iter().enumerate() {
+        let y = row_y(row);
+
+        for (start, end, tile) in runs(tiles, |tile| tile.ground()) {
+            spawned.push(spawn_run(commands, start, end, y, tile.color(), TILE, 0.));
+        }
+
+        for (start, end, tile) in runs(tiles, |tile| *tile) {
+            if let Some((color, height)) = tile.marking() {
+                spawned.push(spawn_run(commands, start, end, y, color, height, 0.1));
+            }
+        }
+    }
+
+    spawned
+}
+
+/// Columns `start..end` of a row that share the same `key`.
+fn runs(tiles: &[Tile], key: impl Fn(&Tile) -> Tile) -> Vec<(usize, usize, Tile)> {
+    let mut runs: Vec<(usize, usize, Tile)> = Vec::new();
+
+    for (column, tile) in tiles.iter().enumerate() {
+        match runs.last_mut() {
+            Some((_, end, last)) if *last == key(tile) => *end = column + 1,
+            _ => runs.push((column, column + 1, key(tile))),
+        }
+    }
+
+    runs
+}
+
+fn spawn_run(
+    commands: &mut Commands,
+    start: usize,
+    end: usize,
+    y: f32,
+    color: Color,
+    height: f32,
+    z: f32,
+) -> Entity {
+    let width = (end - start) as f32 * TILE;
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(column_x(start) + width / 2., y, z),
+            ..default()
+        })
+        .insert(GameBackground)
+        .insert(Name::new("Tiles"))
+        .id()
+}
@@ -0,0 +1,260 @@
+use crate::{player::Player, GameState};
+use bevy::prelude::*;
+
+/// Horizontal bands the iris is drawn with. More look rounder.
+const IRIS_STRIPS: usize = 60;
+/// Drawn over everything, menus included.
+const OVERLAY_Z: i32 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Fade,
+    /// Covers the screen from the left, then uncovers it to the right.
+    Wipe,
+    /// Closes a circle on the chicken, then opens it again.
+    Iris,
+}
+
+impl Effect {
+    /// Time to cover the screen, and again to uncover it.
+    fn half_secs(&self) -> f32 {
+        match self {
+            Effect::Fade => 0.3,
+            Effect::Wipe => 0.35,
+            Effect::Iris => 0.5,
+        }
+    }
+
+    fn pieces(&self) -> usize {
+        match self {
+            Effect::Fade | Effect::Wipe => 1,
+            Effect::Iris => IRIS_STRIPS * 2,
+        }
+    }
+}
+
+enum Change {
+    Set(GameState),
+    Push(GameState),
+    Pop,
+    Replace(GameState),
+}
+
+struct Running {
+    /// Taken once the screen is covered.
+    change: Option<Change>,
+    effect: Effect,
+    timer: Timer,
+    overlay: Option<Entity>,
+    /// Where the iris closes, in window coordinates from the top left.
+    center: Option<Vec2>,
+}
+
+/// Changes the `GameState` behind a screen effect. The switch happens once the screen is
+/// covered, so the new state sets itself up out of sight.
+#[derive(Resource, Default)]
+pub struct Transitions {
+    running: Option<Running>,
+}
+
+impl Transitions {
+    pub fn set(&mut self, state: GameState, effect: Effect) {
+        self.start(Change::Set(state), effect);
+    }
+
+    pub fn push(&mut self, state: GameState, effect: Effect) {
+        self.start(Change::Push(state), effect);
+    }
+
+    pub fn pop(&mut self, effect: Effect) {
+        self.start(Change::Pop, effect);
+    }
+
+    /// Leaves every stacked state for `state`.
+    pub fn replace(&mut self, state: GameState, effect: Effect) {
+        self.start(Change::Replace(state), effect);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Only the first change asked for goes through; the rest are dropped until it is done.
+    fn start(&mut self, change: Change, effect: Effect) {
+        if self.running.is_some() {
+            return;
+        }
+
+        self.running = Some(Running {
+            change: Some(change),
+            effect,
+            timer: Timer::from_seconds(effect.half_secs(), TimerMode::Once),
+            overlay: None,
+            center: None,
+        });
+    }
+}
+
+#[derive(Component)]
+struct TransitionPiece {
+    index: usize,
+}
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transitions>()
+            .add_system(transition_system)
+            .add_system(draw_transition_system.after(transition_system));
+    }
+}
+
+fn transition_system(
+    mut commands: Commands,
+    mut transitions: ResMut<Transitions>,
+    mut game_state: ResMut<State<GameState>>,
+    time: Res<Time>,
+) {
+    let Some(running) = transitions.running.as_mut() else {
+        return;
+    };
+
+    let overlay = *running
+        .overlay
+        .get_or_insert_with(|| spawn_overlay(&mut commands, running.effect));
+
+    running.timer.tick(time.delta());
+
+    if !running.timer.just_finished() {
+        return;
+    }
+
+    match running.change.take() {
+        Some(change) => {
+            match change {
+                Change::Set(state) => game_state.set(state),
+                Change::Push(state) => game_state.push(state),
+                Change::Pop => game_state.pop(),
+                Change::Replace(state) => game_state.replace(state),
+            }
+            .unwrap();
+            running.timer.reset();
+        }
+        None => {
+            commands.entity(overlay).despawn_recursive();
+            transitions.running = None;
+        }
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands, effect: Effect) -> Entity {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            z_index: ZIndex::Global(OVERLAY_Z),
+            ..default()
+        })
+        .insert(Name::new("Transition"))
+        .with_children(|parent| {
+            for index in 0..effect.pieces() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .insert(TransitionPiece { index });
+            }
+        })
+        .id()
+}
+
+/// Lays out the black pieces covering the screen for how far along the effect is.
+fn draw_transition_system(
+    mut transitions: ResMut<Transitions>,
+    mut piece_query: Query<(&TransitionPiece, &mut Style, &mut BackgroundColor)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    camera_query: Query<&GlobalTransform, With<Camera2d>>,
+    windows: Res<Windows>,
+) {
+    let Some(running) = transitions.running.as_mut() else {
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+
+    let covering = running.change.is_some();
+    let progress = running.timer.percent();
+    let cover = if covering { progress } else { 1. - progress };
+
+    // The iris follows the chicken while there is one, and stays where it was last seen.
+    let camera = camera_query
+        .get_single()
+        .map_or(Vec3::ZERO, |camera_tf| camera_tf.translation());
+    if let Some(player_tf) = player_query.iter().next() {
+        let relative = (player_tf.translation() - camera).truncate();
+        running.center = Some(Vec2::new(
+            size.x / 2. + relative.x,
+            size.y / 2. - relative.y,
+        ));
+    }
+    let center = running.center.unwrap_or(size / 2.);
+
+    for (piece, mut style, mut background) in piece_query.iter_mut() {
+        let (left, top, width, height) = match running.effect {
+            Effect::Fade => (0., 0., size.x, size.y),
+            Effect::Wipe if covering => (0., 0., size.x * cover, size.y),
+            Effect::Wipe => (size.x * (1. - cover), 0., size.x * cover, size.y),
+            Effect::Iris => iris_piece(piece.index, cover, center, size),
+        };
+
+        style.position = UiRect {
+            left: Val::Px(left),
+            top: Val::Px(top),
+            ..default()
+        };
+        style.size = Size::new(Val::Px(width.max(0.)), Val::Px(height));
+
+        background.0 = if running.effect == Effect::Fade {
+            Color::rgba(0., 0., 0., cover)
+        } else {
+            Color::BLACK
+        };
+    }
+}
+
+/// One side of a band of the iris: the black either side of the circle's chord.
+fn iris_piece(index: usize, cover: f32, center: Vec2, size: Vec2) -> (f32, f32, f32, f32) {
+    let strip_height = size.y / IRIS_STRIPS as f32;
+    let top = (index / 2) as f32 * strip_height;
+
+    let farthest = [
+        Vec2::ZERO,
+        Vec2::new(size.x, 0.),
+        Vec2::new(0., size.y),
+        size,
+    ]
+    .iter()
+    .map(|corner| corner.distance(center))
+    .fold(0., f32::max);
+    let radius = farthest * (1. - cover);
+    let dy = top + strip_height / 2. - center.y;
+    let half_chord = (radius * radius - dy * dy).max(0.).sqrt();
+
+    if index.is_multiple_of(2) {
+        (0., top, center.x - half_chord, strip_height)
+    } else {
+        let left = center.x + half_chord;
+        (left, top, size.x - left, strip_height)
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{
+    enemy::{
+        move_enemy_system, spawn_enemy_system, TrafficModifiers, Vehicle, ENEMY_DIM, ENEMY_SCALE,
+    },
+    in_game::{in_game_setup_system, GameBackground},
+    net::Lockstep,
+    particles::ParticleSettings,
+    player::Session,
+    GameState, SIDE_WALK, WIN_HEIGHT, WIN_WIDTH,
+};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+/// Keeps the weather apart from the traffic generated from the same seed.
+const WEATHER_SEED_SALT: u64 = 0x3c6e_f372_fe94_f82b;
+/// Levels spent in each time of day.
+const LEVELS_PER_TIME: u8 = 3;
+const WEATHER_SOURCE: &str = "weather";
+const NIGHT_SOURCE: &str = "night";
+
+/// Above the road and the river, below everything on them.
+const DARKNESS_Z: f32 = 0.9;
+const HEADLIGHT_Z: f32 = 0.95;
+const HAZE_Z: f32 = 4.;
+const RAIN_Z: f32 = 4.5;
+
+const RAIN_DROPS: usize = 90;
+const RAIN_SPEED: f32 = 700.;
+/// Rain falls slightly slanted, in pixels sideways per pixel down.
+const RAIN_SLANT: f32 = 0.2;
+/// Share of the way to the wanted overlay colour covered per second.
+const FADE_RATE: f32 = 1.5;
+/// Overlapping beams making up a headlight cone, each starting at the front of the car:
+/// length, height and alpha.
+const HEADLIGHT_BEAMS: [(f32, f32, f32); 3] =
+    [(60., 26., 0.22), (110., 44., 0.14), (160., 64., 0.08)];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeOfDay {
+    Day,
+    Dusk,
+    Night,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Weather {
+    Clear,
+    /// Wet roads: cars take longer to stop.
+    Rain,
+    /// Less can be seen, so cars drive slower and fewer of them.
+    Fog,
+}
+
+impl TimeOfDay {
+    fn darkness(&self) -> Color {
+        match self {
+            TimeOfDay::Day => Color::rgba(0.05, 0.05, 0.2, 0.),
+            TimeOfDay::Dusk => Color::rgba(0.45, 0.2, 0.1, 0.25),
+            TimeOfDay::Night => Color::rgba(0.02, 0.03, 0.15, 0.55),
+        }
+    }
+}
+
+/// Time of day and weather of the level being played. Both follow from the level the traffic
+/// is set to and the seed of the run, so every peer of an online race gets the same.
+#[derive(Resource)]
+pub struct Conditions {
+    pub time: TimeOfDay,
+    pub weather: Weather,
+    /// Day cycle the weather was rolled for.
+    cycle: u8,
+}
+
+impl Conditions {
+    fn for_level(level: u8, seed: u64) -> Self {
+        let index = level.saturating_sub(1) / LEVELS_PER_TIME;
+        let time = match index % 3 {
+            0 => TimeOfDay::Day,
+            1 => TimeOfDay::Dusk,
+            _ => TimeOfDay::Night,
+        };
+        let cycle = index / 3;
+
+        Self {
+            time,
+            weather: Self::roll_weather(cycle, seed),
+            cycle,
+        }
+    }
+
+    /// The first day is always clear.
+    fn roll_weather(cycle: u8, seed: u64) -> Weather {
+        if cycle == 0 {
+            return Weather::Clear;
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed ^ WEATHER_SEED_SALT ^ cycle as u64);
+        match rng.gen_range(0..3) {
+            0 => Weather::Clear,
+            1 => Weather::Rain,
+            _ => Weather::Fog,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Darkness;
+
+#[derive(Component)]
+struct Haze;
+
+#[derive(Component)]
+struct RainDrop;
+
+/// Light thrown ahead of a car. Kept apart from the car, which is despawned on its own.
+#[derive(Component)]
+struct Headlight {
+    car: Entity,
+    /// From the middle of the car, along the way it drives.
+    offset: f32,
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::InGame)
+                .with_system(weather_setup_system.after(in_game_setup_system)),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(
+                    conditions_system
+                        .before(spawn_enemy_system)
+                        .before(move_enemy_system),
+                )
+                .with_system(overlay_system.after(conditions_system))
+                .with_system(rain_system)
+                .with_system(attach_headlights_system)
+                .with_system(headlight_system.after(move_enemy_system)),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(despawn_weather_system));
+    }
+}
+
+fn weather_setup_system(mut commands: Commands, session: Res<Session>) {
+    let conditions = Conditions::for_level(session.level, session.seed);
+    let screen = Vec2::new(WIN_WIDTH, WIN_HEIGHT + SIDE_WALK * 2.);
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: conditions.time.darkness(),
+                custom_size: Some(Vec2::new(WIN_WIDTH, WIN_HEIGHT)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., DARKNESS_Z),
+            ..default()
+        })
+        .insert(Darkness)
+        .insert(GameBackground)
+        .insert(Name::new("Darkness"));
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.8, 0.82, 0.85, 0.),
+                custom_size: Some(screen),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., HAZE_Z),
+            ..default()
+        })
+        .insert(Haze)
+        .insert(GameBackground)
+        .insert(Name::new("Haze"));
+
+    // Rain is only for show and stays out of the seeded traffic generator.
+    let mut rng = thread_rng();
+    for _ in 0..RAIN_DROPS {
+        let x = rng.gen_range(-screen.x / 2.0..screen.x / 2.);
+        let y = rng.gen_range(-screen.y / 2.0..screen.y / 2.);
+
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.7, 0.8, 0.95, 0.45),
+                    custom_size: Some(Vec2::new(1.5, rng.gen_range(10.0..18.))),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: Vec3::new(x, y, RAIN_Z),
+                    rotation: Quat::from_rotation_z(RAIN_SLANT.atan()),
+                    ..default()
+                },
+                visibility: Visibility {
+                    is_visible: conditions.weather == Weather::Rain,
+                },
+                ..default()
+            })
+            .insert(RainDrop)
+            .insert(GameBackground)
+            .insert(Name::new("RainDrop"));
+    }
+
+    commands.insert_resource(conditions);
+}
+
+/// Moves on to the next time of day as levels are cleared, and lets the weather and the dark
+/// change how the traffic drives. Online, that follows the level the peers agreed on rather
+/// than the local one, so the traffic stays in lockstep.
+fn conditions_system(
+    session: Res<Session>,
+    lockstep: Option<Res<Lockstep>>,
+    mut conditions: ResMut<Conditions>,
+    mut traffic: ResMut<TrafficModifiers>,
+) {
+    let level = lockstep.map_or(session.level, |lockstep| lockstep.applied_level);
+    let wanted = Conditions::for_level(level, session.seed);
+    if wanted.time != conditions.time || wanted.cycle != conditions.cycle {
+        *conditions = wanted;
+    }
+
+    match conditions.weather {
+        Weather::Clear => {
+            traffic.speed.clear(WEATHER_SOURCE);
+            traffic.spawn_rate.clear(WEATHER_SOURCE);
+            traffic.braking.clear(WEATHER_SOURCE);
+        }
+        Weather::Rain => {
+            traffic.speed.set(WEATHER_SOURCE, 0.95);
+            traffic.spawn_rate.clear(WEATHER_SOURCE);
+            traffic.braking.set(WEATHER_SOURCE, 0.6);
+        }
+        Weather::Fog => {
+            traffic.speed.set(WEATHER_SOURCE, 0.85);
+            traffic.spawn_rate.set(WEATHER_SOURCE, 0.9);
+            traffic.braking.clear(WEATHER_SOURCE);
+        }
+    }
+
+    if conditions.time == TimeOfDay::Night {
+        traffic.spawn_rate.set(NIGHT_SOURCE, 0.85);
+    } else {
+        traffic.spawn_rate.clear(NIGHT_SOURCE);
+    }
+}
+
+/// Eases the darkness and the haze towards the current conditions.
+fn overlay_system(
+    conditions: Res<Conditions>,
+    settings: Res<ParticleSettings>,
+    mut darkness_query: Query<&mut Sprite, (With<Darkness>, Without<Haze>)>,
+    mut haze_query: Query<&mut Sprite, (With<Haze>, Without<Darkness>)>,
+    mut rain_query: Query<&mut Visibility, With<RainDrop>>,
+    time: Res<Time>,
+) {
+    let step = (FADE_RATE * time.delta_seconds()).min(1.);
+    let ease = |current: Color, wanted: Color| {
+        let current = Vec4::from(current.as_rgba_f32());
+        Color::from(current.lerp(Vec4::from(wanted.as_rgba_f32()), step))
+    };
+
+    for mut sprite in darkness_query.iter_mut() {
+        sprite.color = ease(sprite.color, conditions.time.darkness());
+    }
+
+    let haze = if conditions.weather == Weather::Fog {
+        0.5
+    } else {
+        0.
+    };
+    for mut sprite in haze_query.iter_mut() {
+        let mut wanted = sprite.color;
+        wanted.set_a(haze);
+        sprite.color = ease(sprite.color, wanted);
+    }
+
+    let raining = conditions.weather == Weather::Rain && !settings.reduced;
+    for mut visibility in rain_query.iter_mut() {
+        visibility.is_visible = raining;
+    }
+}
+
+fn rain_system(
+    mut rain_query: Query<(&mut Transform, &Visibility), With<RainDrop>>,
+    time: Res<Time>,
+) {
+    let half = Vec2::new(WIN_WIDTH, WIN_HEIGHT + SIDE_WALK * 2.) / 2.;
+    let fall = RAIN_SPEED * time.delta_seconds();
+
+    for (mut transform, visibility) in rain_query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        transform.translation.y -= fall;
+        transform.translation.x -= fall * RAIN_SLANT;
+
+        if transform.translation.y < -half.y {
+            transform.translation.y += half.y * 2.;
+        }
+        if transform.translation.x < -half.x {
+            transform.translation.x += half.x * 2.;
+        }
+    }
+}
+
+fn attach_headlights_system(mut commands: Commands, car_query: Query<Entity, Added<Vehicle>>) {
+    for car in car_query.iter() {
+        let front = ENEMY_DIM.0 * ENEMY_SCALE / 2.;
+
+        for (length, height, alpha) in HEADLIGHT_BEAMS {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1., 0.95, 0.7, alpha),
+                        custom_size: Some(Vec2::new(length, height)),
+                        ..default()
+                    },
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(Headlight {
+                    car,
+                    offset: front + length / 2.,
+                })
+                .insert(GameBackground)
+                .insert(Name::new("Headlight"));
+        }
+    }
+}
+
+/// Keeps headlights in front of their cars, lit after dark, and drops those of cars that
+/// have left.
+fn headlight_system(
+    mut commands: Commands,
+    conditions: Res<Conditions>,
+    car_query: Query<(&Transform, &Sprite), Without<Headlight>>,
+    mut headlight_query: Query<(Entity, &Headlight, &mut Transform, &mut Visibility)>,
+) {
+    let lit = conditions.time != TimeOfDay::Day;
+
+    for (ent, headlight, mut transform, mut visibility) in headlight_query.iter_mut() {
+        let Ok((car_tf, car_sprite)) = car_query.get(headlight.car) else {
+            commands.entity(ent).despawn();
+            continue;
+        };
+
+        let ahead = if car_sprite.flip_x { -1. } else { 1. };
+        transform.translation = Vec3::new(
+            car_tf.translation.x + headlight.offset * ahead,
+            car_tf.translation.y,
+            HEADLIGHT_Z,
+        );
+        visibility.is_visible = lit;
+    }
+}
+
+fn despawn_weather_system(mut commands: Commands) {
+    commands.remove_resource::<Conditions>();
+}